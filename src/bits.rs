@@ -0,0 +1,115 @@
+//! Reading and writing samples of any width from 1 to 32 bits, packed
+//! MSB-first as the spec requires when samples do not fill whole bytes.
+//! Samples of 16, 24 or 32 bits that start on a byte are stored in the byte
+//! order of the file instead.
+
+use byteorder::ByteOrder;
+use byteordered::Endianness;
+
+/// Reads samples packed MSB-first, possibly crossing byte boundaries.
+pub(crate) struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// Index of the next bit to read, counting from the MSB of the first
+    /// byte
+    bit_inx: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, bit_inx: 0 }
+    }
+
+    /// Reads the next `bits` bits as an unsigned sample, returning `None` if
+    /// there are not enough bits left.
+    pub(crate) fn read(&mut self, bits: u8) -> Option<u32> {
+        debug_assert!((1..=32).contains(&bits));
+        let end = self.bit_inx.checked_add(bits as usize)?;
+        if end > self.bytes.len() * 8 {
+            return None;
+        }
+
+        let mut sample = 0u64;
+        while self.bit_inx < end {
+            let byte = self.bytes[self.bit_inx / 8];
+            let offset = self.bit_inx % 8;
+            // Read as many bits as are left in this byte, up to the end
+            let len = (8 - offset).min(end - self.bit_inx);
+            let chunk = (byte >> (8 - offset - len)) & ((1u16 << len) - 1) as u8;
+            sample = (sample << len) | chunk as u64;
+            self.bit_inx += len;
+        }
+        Some(sample as u32)
+    }
+
+    /// Like [`BitReader::read`], but reads samples of whole bytes that start
+    /// on a byte in the byte order of the file.
+    pub(crate) fn read_in_byte_order(&mut self, bits: u8, endianness: Endianness) -> Option<u32> {
+        let sample = self.read(bits)?;
+        let starts_on_byte = (self.bit_inx - bits as usize).is_multiple_of(8);
+        if starts_on_byte && is_multibyte(bits) && endianness == Endianness::Little {
+            Some(sample.swap_bytes() >> (32 - bits))
+        } else {
+            Some(sample)
+        }
+    }
+}
+
+/// Whether samples of `bits` bits take up more than one whole byte.
+fn is_multibyte(bits: u8) -> bool {
+    bits > 8 && bits.is_multiple_of(8)
+}
+
+/// Writes samples packed MSB-first, padding the final byte with zeros.
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    /// Number of bits used in the last byte, 0 if it is full or there are no
+    /// bytes
+    used_bits: u8,
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            used_bits: 0,
+        }
+    }
+
+    /// Writes the lowest `bits` bits of the sample.
+    pub(crate) fn write(&mut self, sample: u32, bits: u8) {
+        debug_assert!((1..=32).contains(&bits));
+        let mut remaining = bits;
+        while remaining > 0 {
+            if self.used_bits == 0 {
+                self.bytes.push(0);
+            }
+            let free = 8 - self.used_bits;
+            let len = free.min(remaining);
+            let chunk = (sample >> (remaining - len)) & ((1u32 << len) - 1);
+            *self.bytes.last_mut().unwrap() |= (chunk as u8) << (free - len);
+            self.used_bits = (self.used_bits + len) % 8;
+            remaining -= len;
+        }
+    }
+
+    /// Like [`BitWriter::write`], but writes samples of whole bytes that
+    /// start on a byte in the byte order `B`.
+    pub(crate) fn write_in_byte_order<B: ByteOrder>(&mut self, sample: u32, bits: u8) {
+        if self.used_bits == 0 && is_multibyte(bits) {
+            let mut buf = [0; 4];
+            let len = bits as usize / 8;
+            // Higher bits are ignored, as in `write`
+            let sample = sample & (u32::MAX >> (32 - bits));
+            B::write_uint(&mut buf, sample.into(), len);
+            for &byte in &buf[..len] {
+                self.write(byte.into(), 8);
+            }
+        } else {
+            self.write(sample, bits);
+        }
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
@@ -2,6 +2,8 @@ use std::iter::repeat;
 
 use crate::{types::Short, Image};
 
+pub use half::f16;
+
 pub trait Color: private::Sealed {
     type ViewAs: Color;
 
@@ -31,6 +33,58 @@ impl Color for Grayscale8Bit {
     }
 }
 
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Grayscale16Bit(pub u16);
+impl Color for Grayscale16Bit {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+
+/// A pixel of `N` unsigned integer samples of any bit depth from 1 to 32.
+/// The bit depth is chosen when encoding, and any higher bits of a sample are
+/// ignored.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Samples<const N: usize>(pub [u32; N]);
+impl<const N: usize> Color for Samples<N> {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+pub type GenericGrayscale = Samples<1>;
+pub type GenericRGB = Samples<3>;
+
+/// A floating point or signed integer sample, as opposed to the unsigned
+/// integers used by most colors.
+pub trait Sample: private::SampleImpl {}
+
+impl Sample for f16 {}
+impl Sample for f32 {}
+impl Sample for f64 {}
+impl Sample for i8 {}
+impl Sample for i16 {}
+impl Sample for i32 {}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SampleGrayscale<T: Sample>(pub T);
+impl<T: Sample> Color for SampleGrayscale<T> {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+pub type GrayscaleF16 = SampleGrayscale<f16>;
+pub type GrayscaleF32 = SampleGrayscale<f32>;
+pub type GrayscaleF64 = SampleGrayscale<f64>;
+pub type GrayscaleI8 = SampleGrayscale<i8>;
+pub type GrayscaleI16 = SampleGrayscale<i16>;
+pub type GrayscaleI32 = SampleGrayscale<i32>;
+
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct Grayscale4Bit(u8);
 impl Color for Grayscale4Bit {
@@ -80,6 +134,376 @@ impl RGB {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SampleRGB<T: Sample> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
+}
+impl<T: Sample> Color for SampleRGB<T> {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+impl<T: Sample> SampleRGB<T> {
+    pub fn new(r: T, g: T, b: T) -> Self {
+        Self { r, g, b }
+    }
+}
+pub type RGBF16 = SampleRGB<f16>;
+pub type RGBF32 = SampleRGB<f32>;
+pub type RGBF64 = SampleRGB<f64>;
+pub type RGBI8 = SampleRGB<i8>;
+pub type RGBI16 = SampleRGB<i16>;
+pub type RGBI32 = SampleRGB<i32>;
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct RGB16 {
+    pub r: u16,
+    pub g: u16,
+    pub b: u16,
+}
+impl Color for RGB16 {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+impl RGB16 {
+    pub fn new(r: u16, g: u16, b: u16) -> Self {
+        Self { r, g, b }
+    }
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct CMYK {
+    pub c: u8,
+    pub m: u8,
+    pub y: u8,
+    pub k: u8,
+}
+impl Color for CMYK {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+impl CMYK {
+    pub fn new(c: u8, m: u8, y: u8, k: u8) -> Self {
+        Self { c, m, y, k }
+    }
+}
+
+/// Naively converts an RGB color to CMYK, using as much black ink as
+/// possible. This does not use any color profile.
+impl From<RGB> for CMYK {
+    fn from(RGB { r, g, b }: RGB) -> Self {
+        let k = u8::MAX - r.max(g).max(b);
+        if k == u8::MAX {
+            return Self::new(0, 0, 0, k);
+        }
+        let white = (u8::MAX - k) as u16;
+        let ink = |sample: u8| ((white - sample as u16) * 255 + white / 2) / white;
+        Self::new(ink(r) as u8, ink(g) as u8, ink(b) as u8, k)
+    }
+}
+
+/// Naively converts a CMYK color to RGB. This does not use any color
+/// profile.
+impl From<CMYK> for RGB {
+    fn from(CMYK { c, m, y, k }: CMYK) -> Self {
+        let sample = |ink: u8| (((u8::MAX - ink) as u16 * (u8::MAX - k) as u16 + 127) / 255) as u8;
+        Self::new(sample(c), sample(m), sample(y))
+    }
+}
+
+/// A reference white used to convert between [`Lab`] and [`RGB`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WhitePoint {
+    /// Horizon light, used by ICC profiles.
+    #[default]
+    D50,
+    /// Noon daylight, the white point of sRGB.
+    D65,
+}
+
+impl WhitePoint {
+    /// CIE XYZ of the white point, with Y normalized to 1.
+    const fn xyz(self) -> [f32; 3] {
+        match self {
+            WhitePoint::D50 => [0.96422, 1.0, 0.82521],
+            WhitePoint::D65 => [0.95047, 1.0, 1.08883],
+        }
+    }
+}
+
+/// Linear sRGB to CIE XYZ with a D65 white point
+const SRGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.072175],
+    [0.0193339, 0.119192, 0.9503041],
+];
+/// CIE XYZ with a D65 white point to linear sRGB
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.969266, 1.8760108, 0.041556],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+/// Bradford chromatic adaptation from D65 to D50
+const D65_TO_D50: [[f32; 3]; 3] = [
+    [1.0478112, 0.0228866, -0.0501270],
+    [0.0295424, 0.9904844, -0.0170491],
+    [-0.0092345, 0.0150436, 0.7521316],
+];
+/// Bradford chromatic adaptation from D50 to D65
+const D50_TO_D65: [[f32; 3]; 3] = [
+    [0.9555766, -0.0230393, 0.0631636],
+    [-0.0282895, 1.0099416, 0.0210077],
+    [0.0122982, -0.0204830, 1.3299098],
+];
+
+fn multiply(matrix: [[f32; 3]; 3], vector: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+/// A CIE L*a*b* color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lab {
+    /// Lightness from 0 to 100
+    pub l: f32,
+    /// Green (negative) to red (positive)
+    pub a: f32,
+    /// Blue (negative) to yellow (positive)
+    pub b: f32,
+}
+impl Color for Lab {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+impl Lab {
+    const EPSILON: f32 = 216.0 / 24389.0;
+    const KAPPA: f32 = 24389.0 / 27.0;
+
+    pub fn new(l: f32, a: f32, b: f32) -> Self {
+        Self { l, a, b }
+    }
+
+    /// Converts an sRGB color to L*a*b* relative to the given white point.
+    pub fn from_rgb(RGB { r, g, b }: RGB, white_point: WhitePoint) -> Self {
+        let linear = [r, g, b].map(|sample| {
+            let sample = sample as f32 / 255.0;
+            if sample <= 0.04045 {
+                sample / 12.92
+            } else {
+                ((sample + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        let mut xyz = multiply(SRGB_TO_XYZ, linear);
+        if white_point == WhitePoint::D50 {
+            xyz = multiply(D65_TO_D50, xyz);
+        }
+        let white = white_point.xyz();
+        let [fx, fy, fz] = [0, 1, 2].map(|inx| {
+            let ratio = xyz[inx] / white[inx];
+            if ratio > Self::EPSILON {
+                ratio.cbrt()
+            } else {
+                (Self::KAPPA * ratio + 16.0) / 116.0
+            }
+        });
+        Self::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    /// Converts to sRGB, treating the color as relative to the given white
+    /// point. Colors outside of sRGB are clipped.
+    pub fn to_rgb(self, white_point: WhitePoint) -> RGB {
+        let fy = (self.l + 16.0) / 116.0;
+        let fx = fy + self.a / 500.0;
+        let fz = fy - self.b / 200.0;
+        let ratios = [
+            if fx.powi(3) > Self::EPSILON {
+                fx.powi(3)
+            } else {
+                (116.0 * fx - 16.0) / Self::KAPPA
+            },
+            if self.l > Self::KAPPA * Self::EPSILON {
+                fy.powi(3)
+            } else {
+                self.l / Self::KAPPA
+            },
+            if fz.powi(3) > Self::EPSILON {
+                fz.powi(3)
+            } else {
+                (116.0 * fz - 16.0) / Self::KAPPA
+            },
+        ];
+        let white = white_point.xyz();
+        let mut xyz = [0, 1, 2].map(|inx| ratios[inx] * white[inx]);
+        if white_point == WhitePoint::D50 {
+            xyz = multiply(D50_TO_D65, xyz);
+        }
+        let [r, g, b] = multiply(XYZ_TO_SRGB, xyz).map(|sample| {
+            let sample = if sample <= 0.0031308 {
+                12.92 * sample
+            } else {
+                1.055 * sample.powf(1.0 / 2.4) - 0.055
+            };
+            (sample * 255.0).round().clamp(0.0, 255.0) as u8
+        });
+        RGB::new(r, g, b)
+    }
+}
+
+/// How the samples of a [`Lab`] color are stored in 8 bits each.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LabEncoding {
+    /// CIELab, with signed a* and b* samples.
+    #[default]
+    Cie,
+    /// ICCLab, with a* and b* samples offset by 128.
+    Icc,
+    /// ITULab, with samples scaled to the default ranges of TIFF-FX: L* from
+    /// 0 to 100, a* from -85 to 85 and b* from -75 to 125.
+    Itu,
+}
+
+impl LabEncoding {
+    pub(crate) fn encode_samples(self, Lab { l, a, b }: Lab) -> [u8; 3] {
+        fn scale(value: f32, min: f32, max: f32) -> u8 {
+            ((value - min) * 255.0 / (max - min))
+                .round()
+                .clamp(0.0, 255.0) as u8
+        }
+        let l = scale(l, 0.0, 100.0);
+        match self {
+            LabEncoding::Cie => {
+                let [a, b] = [a, b].map(|sample| sample.round().clamp(-128.0, 127.0) as i8 as u8);
+                [l, a, b]
+            }
+            LabEncoding::Icc => [l, scale(a, -128.0, 127.0), scale(b, -128.0, 127.0)],
+            LabEncoding::Itu => [l, scale(a, -85.0, 85.0), scale(b, -75.0, 125.0)],
+        }
+    }
+
+    pub(crate) fn decode_samples(self, [l, a, b]: [u8; 3]) -> Lab {
+        fn unscale(sample: u8, min: f32, max: f32) -> f32 {
+            sample as f32 * (max - min) / 255.0 + min
+        }
+        let l = unscale(l, 0.0, 100.0);
+        match self {
+            LabEncoding::Cie => Lab::new(l, a as i8 as f32, b as i8 as f32),
+            LabEncoding::Icc => Lab::new(l, unscale(a, -128.0, 127.0), unscale(b, -128.0, 127.0)),
+            LabEncoding::Itu => Lab::new(l, unscale(a, -85.0, 85.0), unscale(b, -75.0, 125.0)),
+        }
+    }
+}
+
+/// How the alpha sample of a color relates to its other samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alpha {
+    /// The other samples have already been multiplied by the alpha
+    /// (premultiplied alpha).
+    Associated,
+    /// The other samples are independent of the alpha.
+    Unassociated,
+}
+
+/// A color with an alpha sample.
+pub trait AlphaColor: Color {
+    /// Converts a color with unassociated alpha into one with associated
+    /// alpha by multiplying the other samples by the alpha.
+    fn associate(self) -> Self;
+
+    /// Converts a color with associated alpha into one with unassociated
+    /// alpha by dividing the other samples by the alpha. Samples of fully
+    /// transparent colors become 0.
+    fn unassociate(self) -> Self;
+}
+
+fn associate_sample(sample: u8, alpha: u8) -> u8 {
+    ((sample as u16 * alpha as u16 + 127) / 255) as u8
+}
+
+fn unassociate_sample(sample: u8, alpha: u8) -> u8 {
+    match alpha {
+        0 => 0,
+        _ => ((sample as u16 * 255 + alpha as u16 / 2) / alpha as u16).min(255) as u8,
+    }
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct RGBA {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+impl Color for RGBA {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+impl AlphaColor for RGBA {
+    fn associate(self) -> Self {
+        Self::new(
+            associate_sample(self.r, self.a),
+            associate_sample(self.g, self.a),
+            associate_sample(self.b, self.a),
+            self.a,
+        )
+    }
+
+    fn unassociate(self) -> Self {
+        Self::new(
+            unassociate_sample(self.r, self.a),
+            unassociate_sample(self.g, self.a),
+            unassociate_sample(self.b, self.a),
+            self.a,
+        )
+    }
+}
+impl RGBA {
+    pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct GrayscaleAlpha {
+    pub gray: u8,
+    pub alpha: u8,
+}
+impl Color for GrayscaleAlpha {
+    type ViewAs = Self;
+
+    fn view(&self) -> Self::ViewAs {
+        *self
+    }
+}
+impl AlphaColor for GrayscaleAlpha {
+    fn associate(self) -> Self {
+        Self::new(associate_sample(self.gray, self.alpha), self.alpha)
+    }
+
+    fn unassociate(self) -> Self {
+        Self::new(unassociate_sample(self.gray, self.alpha), self.alpha)
+    }
+}
+impl GrayscaleAlpha {
+    pub fn new(gray: u8, alpha: u8) -> Self {
+        Self { gray, alpha }
+    }
+}
+
 #[derive(Clone)]
 pub struct ColorMap {
     to_rgb: Vec<RGB>,
@@ -91,6 +515,13 @@ impl ColorMap {
         Self { to_rgb: Vec::new() }
     }
 
+    /// Creates a color map with colors at the given indices, keeping any
+    /// duplicates so that existing indices stay valid.
+    pub(crate) fn from_colors(to_rgb: Vec<RGB>) -> Self {
+        debug_assert!(to_rgb.len() <= Self::MAX_COLORS);
+        Self { to_rgb }
+    }
+
     /// Returns a palettized image with the given pixels, using only colors in
     /// the palette.
     ///
@@ -105,6 +536,24 @@ impl ColorMap {
             .map(|palettized_pixels| Image::new(palettized_pixels, width, height))
     }
 
+    /// Returns a palettized image with the given indices into the palette.
+    ///
+    /// Returns `None` if an index is not in the palette.
+    pub fn try_new_indexed_image<'a>(
+        &'a self,
+        indices: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Option<Image<PaletteColor<'a>>> {
+        indices
+            .iter()
+            .map(|&inx| {
+                (usize::from(inx) < self.to_rgb.len()).then(|| PaletteColor::new(self, inx))
+            })
+            .collect::<Option<_>>()
+            .map(|palettized_pixels| Image::new(palettized_pixels, width, height))
+    }
+
     /// Adds a color to the palette if it is not already in the palette, and
     /// there is space. Returns how many colors have been added if successfully
     /// added or if the color was already in the palette.
@@ -116,6 +565,11 @@ impl ColorMap {
         self.to_rgb.contains(&c)
     }
 
+    /// The colors in the palette, in index order.
+    pub fn colors(&self) -> &[RGB] {
+        &self.to_rgb
+    }
+
     pub(crate) fn bits_per_palette_sample(&self) -> Short {
         if self.to_rgb.len() <= 16 {
             4
@@ -131,11 +585,13 @@ impl ColorMap {
             .iter()
             .copied()
             .chain(repeat(RGB::new(0, 0, 0)).take(remaining));
+        // Color map values range from 0 to 65535, so scale each channel so
+        // that 255 maps to 65535
         colors
             .clone()
-            .map(|color| color.r as Short)
-            .chain(colors.clone().map(|color| color.g as Short))
-            .chain(colors.clone().map(|color| color.b as Short))
+            .map(|color| color.r as Short * 257)
+            .chain(colors.clone().map(|color| color.g as Short * 257))
+            .chain(colors.clone().map(|color| color.b as Short * 257))
             .collect()
     }
 
@@ -212,13 +668,268 @@ impl<'a> PaletteColor<'a> {
 }
 
 mod private {
-    use super::{Bilevel, Grayscale4Bit, Grayscale8Bit, PaletteColor, RGB};
+    use byteorder::ByteOrder;
+    use byteordered::{Endian, Endianness};
+
+    use crate::ifd;
+
+    use super::{
+        f16, Bilevel, Grayscale16Bit, Grayscale4Bit, Grayscale8Bit, GrayscaleAlpha, Lab,
+        PaletteColor, Sample, SampleGrayscale, SampleRGB, Samples, CMYK, RGB, RGB16, RGBA,
+    };
 
     pub trait Sealed {}
 
+    pub trait SampleImpl: Copy {
+        const BITS: u16;
+        const FORMAT: ifd::tags::SampleFormat;
+
+        fn write<E: ByteOrder>(self, buff: &mut [u8]);
+        fn read(endianness: Endianness, buff: &[u8]) -> Self;
+
+        /// Returns the bytes of the sample in the given byte order.
+        fn encode<E: ByteOrder>(self) -> std::iter::Take<std::array::IntoIter<u8, 8>> {
+            let mut buff = [0; 8];
+            self.write::<E>(&mut buff);
+            buff.into_iter().take(Self::BITS as usize / 8)
+        }
+    }
+
+    impl SampleImpl for f16 {
+        const BITS: u16 = 16;
+        const FORMAT: ifd::tags::SampleFormat = ifd::tags::SampleFormat::Float;
+
+        fn write<E: ByteOrder>(self, buff: &mut [u8]) {
+            E::write_u16(buff, self.to_bits())
+        }
+
+        fn read(endianness: Endianness, buff: &[u8]) -> Self {
+            f16::from_bits(endianness.read_u16(buff).unwrap())
+        }
+    }
+
+    impl SampleImpl for f32 {
+        const BITS: u16 = 32;
+        const FORMAT: ifd::tags::SampleFormat = ifd::tags::SampleFormat::Float;
+
+        fn write<E: ByteOrder>(self, buff: &mut [u8]) {
+            E::write_f32(buff, self)
+        }
+
+        fn read(endianness: Endianness, buff: &[u8]) -> Self {
+            endianness.read_f32(buff).unwrap()
+        }
+    }
+
+    impl SampleImpl for f64 {
+        const BITS: u16 = 64;
+        const FORMAT: ifd::tags::SampleFormat = ifd::tags::SampleFormat::Float;
+
+        fn write<E: ByteOrder>(self, buff: &mut [u8]) {
+            E::write_f64(buff, self)
+        }
+
+        fn read(endianness: Endianness, buff: &[u8]) -> Self {
+            endianness.read_f64(buff).unwrap()
+        }
+    }
+
+    impl SampleImpl for i8 {
+        const BITS: u16 = 8;
+        const FORMAT: ifd::tags::SampleFormat = ifd::tags::SampleFormat::Signed;
+
+        fn write<E: ByteOrder>(self, buff: &mut [u8]) {
+            buff[0] = self as u8
+        }
+
+        fn read(_endianness: Endianness, buff: &[u8]) -> Self {
+            buff[0] as i8
+        }
+    }
+
+    impl SampleImpl for i16 {
+        const BITS: u16 = 16;
+        const FORMAT: ifd::tags::SampleFormat = ifd::tags::SampleFormat::Signed;
+
+        fn write<E: ByteOrder>(self, buff: &mut [u8]) {
+            E::write_i16(buff, self)
+        }
+
+        fn read(endianness: Endianness, buff: &[u8]) -> Self {
+            endianness.read_i16(buff).unwrap()
+        }
+    }
+
+    impl SampleImpl for i32 {
+        const BITS: u16 = 32;
+        const FORMAT: ifd::tags::SampleFormat = ifd::tags::SampleFormat::Signed;
+
+        fn write<E: ByteOrder>(self, buff: &mut [u8]) {
+            E::write_i32(buff, self)
+        }
+
+        fn read(endianness: Endianness, buff: &[u8]) -> Self {
+            endianness.read_i32(buff).unwrap()
+        }
+    }
+
     impl Sealed for Bilevel {}
     impl Sealed for Grayscale8Bit {}
+    impl Sealed for Grayscale16Bit {}
     impl Sealed for Grayscale4Bit {}
     impl Sealed for RGB {}
+    impl Sealed for RGB16 {}
+    impl Sealed for RGBA {}
+    impl Sealed for CMYK {}
+    impl Sealed for Lab {}
+    impl<T: Sample> Sealed for SampleGrayscale<T> {}
+    impl<T: Sample> Sealed for SampleRGB<T> {}
+    impl<const N: usize> Sealed for Samples<N> {}
+    impl Sealed for GrayscaleAlpha {}
     impl<'a> Sealed for PaletteColor<'a> {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AlphaColor, GrayscaleAlpha, Lab, LabEncoding, WhitePoint, CMYK, RGB, RGBA};
+
+    #[test]
+    fn associate_and_unassociate_alpha() {
+        let color = RGBA::new(200, 100, 50, 128);
+        let associated = color.associate();
+        assert!(associated == RGBA::new(100, 50, 25, 128));
+        assert!(associated.unassociate() == RGBA::new(199, 100, 50, 128));
+
+        // Opaque colors are unchanged
+        let opaque = RGBA::new(200, 100, 50, 255);
+        assert!(opaque.associate() == opaque);
+        assert!(opaque.unassociate() == opaque);
+
+        // Fully transparent colors become 0, and samples above the alpha
+        // are clamped
+        assert!(RGBA::new(9, 9, 9, 0).unassociate() == RGBA::new(0, 0, 0, 0));
+        assert!(GrayscaleAlpha::new(200, 100).unassociate() == GrayscaleAlpha::new(255, 100));
+        assert!(GrayscaleAlpha::new(255, 51).associate() == GrayscaleAlpha::new(51, 51));
+    }
+
+    #[test]
+    fn rgb_to_cmyk() {
+        for (rgb, cmyk) in [
+            (RGB::new(255, 255, 255), CMYK::new(0, 0, 0, 0)),
+            (RGB::new(0, 0, 0), CMYK::new(0, 0, 0, 255)),
+            (RGB::new(255, 0, 0), CMYK::new(0, 255, 255, 0)),
+            (RGB::new(128, 64, 0), CMYK::new(0, 128, 255, 127)),
+        ] {
+            assert!(CMYK::from(rgb) == cmyk);
+            assert!(RGB::from(cmyk) == rgb);
+        }
+        // Without black ink, the other inks make up the color
+        assert!(RGB::from(CMYK::new(255, 0, 0, 0)) == RGB::new(0, 255, 255));
+    }
+
+    #[test]
+    fn rgb_to_cmyk_and_back_is_close() {
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(17) {
+                for b in (0..=255).step_by(51) {
+                    let rgb = RGB::new(r, g, b);
+                    let back = RGB::from(CMYK::from(rgb));
+                    assert!(
+                        [(rgb.r, back.r), (rgb.g, back.g), (rgb.b, back.b)]
+                            .iter()
+                            .all(|(sample, back)| sample.abs_diff(*back) <= 1),
+                        "{r} {g} {b}"
+                    );
+                }
+            }
+        }
+    }
+
+    fn assert_lab_close(lab: Lab, expected: Lab, tolerance: f32) {
+        assert!(
+            (lab.l - expected.l).abs() <= tolerance
+                && (lab.a - expected.a).abs() <= tolerance
+                && (lab.b - expected.b).abs() <= tolerance,
+            "{lab:?} is not {expected:?}"
+        );
+    }
+
+    #[test]
+    fn rgb_to_lab() {
+        for white_point in [WhitePoint::D50, WhitePoint::D65] {
+            let white = RGB::new(255, 255, 255);
+            assert_lab_close(
+                Lab::from_rgb(white, white_point),
+                Lab::new(100.0, 0.0, 0.0),
+                0.05,
+            );
+            assert!(Lab::new(100.0, 0.0, 0.0).to_rgb(white_point) == white);
+            let black = RGB::new(0, 0, 0);
+            assert_lab_close(
+                Lab::from_rgb(black, white_point),
+                Lab::new(0.0, 0.0, 0.0),
+                0.05,
+            );
+            assert!(Lab::new(0.0, 0.0, 0.0).to_rgb(white_point) == black);
+        }
+        // Red relative to each white point
+        let red = RGB::new(255, 0, 0);
+        assert_lab_close(
+            Lab::from_rgb(red, WhitePoint::D65),
+            Lab::new(53.24, 80.09, 67.20),
+            0.05,
+        );
+        assert_lab_close(
+            Lab::from_rgb(red, WhitePoint::D50),
+            Lab::new(54.29, 80.80, 69.89),
+            0.05,
+        );
+    }
+
+    #[test]
+    fn rgb_to_lab_and_back_is_close() {
+        for white_point in [WhitePoint::D50, WhitePoint::D65] {
+            for r in (0..=255).step_by(15) {
+                for g in (0..=255).step_by(17) {
+                    for b in (0..=255).step_by(51) {
+                        let rgb = RGB::new(r, g, b);
+                        let back = Lab::from_rgb(rgb, white_point).to_rgb(white_point);
+                        assert!(
+                            [(rgb.r, back.r), (rgb.g, back.g), (rgb.b, back.b)]
+                                .iter()
+                                .all(|(sample, back)| sample.abs_diff(*back) <= 1),
+                            "{r} {g} {b} {white_point:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lab_encodings() {
+        let white = Lab::new(100.0, 0.0, 0.0);
+        for (encoding, samples) in [
+            (LabEncoding::Cie, [255, 0, 0]),
+            (LabEncoding::Icc, [255, 128, 128]),
+            (LabEncoding::Itu, [255, 128, 96]),
+        ] {
+            assert_eq!(encoding.encode_samples(white), samples);
+            // ICCLab and ITULab can only get within half a step of a* and b*
+            assert_lab_close(encoding.decode_samples(samples), white, 0.5);
+        }
+        // CIELab stores a* and b* as signed bytes
+        assert_eq!(
+            LabEncoding::Cie.encode_samples(Lab::new(50.0, -20.0, 30.0)),
+            [128, 236, 30]
+        );
+        for encoding in [LabEncoding::Cie, LabEncoding::Icc, LabEncoding::Itu] {
+            for samples in [[0, 0, 0], [17, 200, 99], [255, 255, 255], [128, 127, 128]] {
+                assert_eq!(
+                    encoding.encode_samples(encoding.decode_samples(samples)),
+                    samples
+                );
+            }
+        }
+    }
+}
@@ -2,9 +2,71 @@ use super::{DecodeError, ImageInfo};
 use crate::{colors, ifd, Image};
 
 pub(crate) fn decode_image(
-    fields: Vec<ifd::Entry>,
+    bytes: &[u8],
+    _fields: Vec<ifd::Entry>,
     info: ImageInfo,
     white_is_zero: bool,
 ) -> Result<Image<colors::Bilevel>, DecodeError> {
-    todo!()
+    let to_pixel = if white_is_zero {
+        |bit| match bit {
+            0 => colors::Bilevel::White,
+            _ => colors::Bilevel::Black,
+        }
+    } else {
+        |bit| match bit {
+            0 => colors::Bilevel::Black,
+            _ => colors::Bilevel::White,
+        }
+    };
+
+    // Each row starts on a new byte, with the highest order bits first
+    let bytes_per_row = info.width.div_ceil(8);
+    let pixels = info
+        .read_rows(bytes, &[1])?
+        .chunks_exact(bytes_per_row)
+        .flat_map(|row| {
+            row.iter()
+                .flat_map(|byte| (0..8).rev().map(move |bit| (byte >> bit) & 1))
+                .take(info.width)
+                .map(to_pixel)
+        })
+        .collect();
+
+    Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use crate::{
+        colors::Bilevel,
+        encode::{
+            bilevel::{BilevelImageEncoder, BlackIsZero, WhiteIsZero},
+            compression::{NoCompression, PackBits},
+            ImageEncoder,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    #[test]
+    fn odd_widths() {
+        for width in [1, 3, 7, 9, 13] {
+            let pixels = (0..width * 3)
+                .map(|i| match i % 3 {
+                    0 => Bilevel::White,
+                    _ => Bilevel::Black,
+                })
+                .collect();
+            let image = Image::new(pixels, width, 3);
+            for encoded in [
+                BilevelImageEncoder::<LittleEndian, _, _>::new(&image, NoCompression, BlackIsZero)
+                    .encode(),
+                BilevelImageEncoder::<BigEndian, _, _>::new(&image, PackBits, WhiteIsZero).encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
 }
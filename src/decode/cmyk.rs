@@ -0,0 +1,53 @@
+use super::{read_samples_per_pixel, tiff_field, CMYKImage, DecodeError, ImageInfo};
+use crate::{colors, ifd, Image};
+
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+) -> Result<CMYKImage, DecodeError> {
+    let ink_set = match tiff_field::find(&fields, ifd::Tag::InkSet) {
+        Some(values) => tiff_field::read_single_short(values).and_then(|ink_set| {
+            // TODO: Use DecodeError::InvalidTagValues
+            ifd::tags::InkSet::from_repr(ink_set).ok_or(DecodeError::TiffFieldError)
+        })?,
+        None => Default::default(),
+    };
+    if ink_set != ifd::tags::InkSet::Cmyk {
+        return Err(DecodeError::UnsupportedInkSet);
+    }
+    let samples_per_pixel = read_samples_per_pixel(&fields)?;
+    let number_of_inks = match tiff_field::find(&fields, ifd::Tag::NumberOfInks) {
+        Some(values) => tiff_field::read_single_short(values)?,
+        None => samples_per_pixel,
+    };
+    if samples_per_pixel != 4 || number_of_inks != 4 {
+        return Err(DecodeError::UnsupportedSamplesPerPixel(samples_per_pixel));
+    }
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?;
+    if bits_per_sample != [8, 8, 8, 8] {
+        return Err(DecodeError::UnsupportedBitsPerSample(
+            bits_per_sample.to_vec(),
+        ));
+    }
+    let ink_names = match tiff_field::find(&fields, ifd::Tag::InkNames) {
+        Some(values) => Some(
+            tiff_field::read_strings(values)?
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        ),
+        None => None,
+    };
+
+    let pixels = info
+        .read_rows(bytes, bits_per_sample)?
+        .chunks_exact(4)
+        .map(|sample| colors::CMYK::new(sample[0], sample[1], sample[2], sample[3]))
+        .collect();
+
+    let image =
+        Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)?;
+    Ok(CMYKImage { image, ink_names })
+}
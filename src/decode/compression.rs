@@ -18,28 +18,395 @@ pub(crate) fn tag_to_decompressor(
     }
 }
 
+/// Reads bytes compressed using PackBits compression.
+///
+/// Returns an error if a run is cut off by the end of the strip. Stops once
+/// more than `expected_len` bytes have been decompressed.
+pub(crate) fn packbits(bytes: &[u8], expected_len: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut decompressed = Vec::with_capacity(expected_len);
+    let mut bytes = bytes.iter().copied();
+
+    while let Some(n) = bytes.next() {
+        match n as i8 {
+            // If n is between 0 and 127 inclusive, copy the next n+1 bytes literally
+            0..=127 => {
+                let len = n as usize + 1;
+                for _ in 0..len {
+                    let byte = bytes.next().ok_or(DecodeError::InvalidCompressedData)?;
+                    decompressed.push(byte);
+                }
+            }
+            // Else if n is -128, noop
+            -128 => (),
+            // Else if n is between -127 and -1 inclusive, copy the next byte -n+1 times
+            n => {
+                let len = (-(n as isize)) as usize + 1;
+                let byte = bytes.next().ok_or(DecodeError::InvalidCompressedData)?;
+                decompressed.extend(std::iter::repeat_n(byte, len));
+            }
+        }
+        // There is more data than the strip can hold, which the caller reports
+        if decompressed.len() > expected_len {
+            break;
+        }
+    }
+
+    Ok(decompressed)
+}
+
+/// Reads bytes compressed using LZW compression.
+///
+/// Strips written by libtiff before version 5.0 pack codes LSB-first and
+/// switch code widths one code later than the spec. These are detected from
+/// the leading ClearCode and decoded too.
+///
+/// Stops once more than `expected_len` bytes have been decompressed.
+pub(crate) fn lzw(bytes: &[u8], expected_len: usize) -> Result<Vec<u8>, DecodeError> {
+    const CLEAR_CODE: u16 = 256;
+    const END_OF_INFORMATION_CODE: u16 = 257;
+    const FIRST_CODE: u16 = 258;
+    // Codes should only be up to 12 bits
+    const MAX_BITCOUNT: u8 = 12;
+    const TABLE_SIZE: usize = 1 << MAX_BITCOUNT;
+
+    // A new-style ClearCode begins with 0b1000_0000, while an old-style one
+    // begins with a zero byte followed by the high bit of the code.
+    let old_style = matches!(bytes, [0, second, ..] if second & 1 == 1);
+    let mut codes = LzwCodeReader::new(bytes, old_style);
+
+    let mut decompressed = Vec::with_capacity(expected_len);
+    // Every string in the table already appears in the decompressed output,
+    // so entries are stored as the (start, len) of one of those appearances.
+    let mut string_table: Vec<(usize, usize)> = Vec::new();
+    let mut bitcount = 9;
+    // The (start, len) in the output of the string for the previous code
+    let mut prev: Option<(usize, usize)> = None;
+
+    while let Some(code) = codes.next(bitcount) {
+        let start = decompressed.len();
+        match code {
+            CLEAR_CODE => {
+                string_table.clear();
+                bitcount = 9;
+                prev = None;
+                continue;
+            }
+            END_OF_INFORMATION_CODE => break,
+            0..=255 => decompressed.push(code as u8),
+            _ => match (string_table.get((code - FIRST_CODE) as usize), prev) {
+                (Some(&(string_start, len)), _) => {
+                    decompressed.extend_from_within(string_start..string_start + len)
+                }
+                // The code is the one about to be added to the table, so its
+                // string is the previous string plus its own first byte
+                (None, Some((prev_start, prev_len)))
+                    if (code - FIRST_CODE) as usize == string_table.len() =>
+                {
+                    decompressed.extend_from_within(prev_start..prev_start + prev_len);
+                    decompressed.push(decompressed[prev_start]);
+                }
+                _ => return Err(DecodeError::InvalidCompressedData),
+            },
+        }
+        // There is more data than the strip can hold, which the caller reports
+        if decompressed.len() > expected_len {
+            break;
+        }
+
+        if let Some((prev_start, prev_len)) = prev {
+            // The previous string followed by the first byte of this one
+            if FIRST_CODE as usize + string_table.len() < TABLE_SIZE {
+                string_table.push((prev_start, prev_len + 1));
+            }
+            let next_code = FIRST_CODE as usize + string_table.len();
+            // New-style codes switch width one code early
+            let switch_at = if old_style { next_code } else { next_code + 1 };
+            if switch_at >= 1 << bitcount && bitcount < MAX_BITCOUNT {
+                bitcount += 1;
+            }
+        }
+        prev = Some((start, decompressed.len() - start));
+    }
+
+    Ok(decompressed)
+}
+
+/// Reads variable width LZW codes from packed bits.
+struct LzwCodeReader<'a> {
+    bytes: &'a [u8],
+    bit_inx: usize,
+    lsb_first: bool,
+}
+
+impl<'a> LzwCodeReader<'a> {
+    fn new(bytes: &'a [u8], lsb_first: bool) -> Self {
+        Self {
+            bytes,
+            bit_inx: 0,
+            lsb_first,
+        }
+    }
+
+    /// Returns `None` once there are not enough bits left for a whole code.
+    fn next(&mut self, bitcount: u8) -> Option<u16> {
+        if self.bit_inx + bitcount as usize > self.bytes.len() * 8 {
+            return None;
+        }
+        let mut code = 0;
+        for i in 0..bitcount {
+            let byte = self.bytes[self.bit_inx / 8];
+            let bit_in_byte = self.bit_inx % 8;
+            if self.lsb_first {
+                code |= (((byte >> bit_in_byte) & 1) as u16) << i;
+            } else {
+                code = (code << 1) | ((byte >> (7 - bit_in_byte)) & 1) as u16;
+            }
+            self.bit_inx += 1;
+        }
+        Some(code)
+    }
+}
+
 pub(crate) mod sealed {
-    use crate::compression::{Lzw, NoCompression, PackBits};
+    use crate::{
+        compression::{Lzw, NoCompression, PackBits},
+        decode::DecodeError,
+    };
+
+    use super::{lzw, packbits};
 
     pub trait DecompressionImpl {
-        fn decompress<'a>(&self, bytes: &'a [u8]) -> Box<dyn Iterator<Item = u8> + 'a>;
+        /// Decompresses a single strip, which should hold `expected_len` bytes
+        /// once decompressed. Decompression may stop early once past
+        /// `expected_len` bytes, so that overlong data doesn't use up memory.
+        fn decompress(&self, bytes: &[u8], expected_len: usize) -> Result<Vec<u8>, DecodeError>;
     }
 
     impl DecompressionImpl for NoCompression {
-        fn decompress<'a>(&self, bytes: &'a [u8]) -> Box<dyn Iterator<Item = u8> + 'a> {
-            Box::new(bytes.into_iter().copied())
+        fn decompress(&self, bytes: &[u8], _expected_len: usize) -> Result<Vec<u8>, DecodeError> {
+            Ok(bytes.to_vec())
         }
     }
 
     impl DecompressionImpl for PackBits {
-        fn decompress<'a>(&self, bytes: &'a [u8]) -> Box<dyn Iterator<Item = u8> + 'a> {
-            todo!()
+        fn decompress(&self, bytes: &[u8], expected_len: usize) -> Result<Vec<u8>, DecodeError> {
+            packbits(bytes, expected_len)
         }
     }
 
     impl DecompressionImpl for Lzw {
-        fn decompress<'a>(&self, bytes: &'a [u8]) -> Box<dyn Iterator<Item = u8> + 'a> {
-            todo!()
+        fn decompress(&self, bytes: &[u8], expected_len: usize) -> Result<Vec<u8>, DecodeError> {
+            lzw(bytes, expected_len)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use byteorder::LittleEndian;
+
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        colors::Grayscale8Bit,
+        compression::Compression,
+        encode::{grayscale_8bit::*, ImageEncoder},
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    /// Encodes the samples as an 8-bit grayscale image with a single strip,
+    /// then checks that they decode to the same samples.
+    fn assert_round_trip<C: Compression<Grayscale8Bit>>(
+        compression: C,
+        samples: &[u8],
+        width: usize,
+    ) {
+        let pixels = samples.iter().copied().map(Grayscale8Bit).collect();
+        let image = Image::new(pixels, width, samples.len() / width);
+        let encoded =
+            Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(&image, compression, BlackIsZero)
+                .encode();
+        assert_decodes_to(&encoded, &image);
+    }
+
+    /// Pseudorandom bytes, which compress poorly.
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn packbits_runs_of_128() {
+        // The longest replicate run
+        assert_eq!(packbits(&[-127i8 as u8, 7], 128).unwrap(), [7; 128]);
+        // The longest literal run
+        let literal: Vec<u8> = (0..128).collect();
+        let packed: Vec<u8> = [127].into_iter().chain(literal.iter().copied()).collect();
+        assert_eq!(packbits(&packed, 128).unwrap(), literal);
+        // -128 is skipped
+        assert_eq!(packbits(&[-128i8 as u8, 0, 9], 1).unwrap(), [9]);
+    }
+
+    #[test]
+    fn packbits_round_trip() {
+        for len in [1, 2, 127, 128, 129, 255, 256, 257, 300] {
+            // A replicate run, then a literal run, then a replicate run
+            let mut row = vec![3; len];
+            row.extend(noise(len, len as u64));
+            row.extend(vec![200; len]);
+            assert_round_trip(PackBits, &row, row.len());
+            assert_round_trip(PackBits, &[row.clone(), row].concat(), len * 3);
+        }
+    }
+
+    /// Packs 9-bit codes MSB-first, as new-style LZW does before the table
+    /// grows past 511 codes.
+    fn pack_9_bit_codes(codes: &[u16]) -> Vec<u8> {
+        let bits: Vec<bool> = codes
+            .iter()
+            .flat_map(|code| (0..9).rev().map(move |i| (code >> i) & 1 == 1))
+            .collect();
+        bits.chunks(8)
+            .map(|byte| {
+                byte.iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, &bit)| acc | ((bit as u8) << (7 - i)))
+            })
+            .collect()
+    }
+
+    /// Compresses like libtiff before version 5.0, packing codes LSB-first
+    /// and switching code widths one code later than the spec.
+    fn old_style_lzw(data: &[u8]) -> Vec<u8> {
+        fn append_code(bits: &mut Vec<bool>, code: u16, bitcount: u8) {
+            bits.extend((0..bitcount).map(|i| (code >> i) & 1 == 1));
+        }
+        fn get_code(string_table: &HashMap<Vec<u8>, u16>, string: &[u8]) -> u16 {
+            match string {
+                [byte] => *byte as u16,
+                string => string_table[string],
+            }
+        }
+
+        let mut bits = Vec::new();
+        let mut string_table = HashMap::new();
+        let mut bitcount = 9;
+        append_code(&mut bits, 256, bitcount);
+        let mut curr = vec![data[0]];
+        for &byte in &data[1..] {
+            let mut string = curr.clone();
+            string.push(byte);
+            if string_table.contains_key(&string) {
+                curr = string;
+                continue;
+            }
+            append_code(&mut bits, get_code(&string_table, &curr), bitcount);
+            let next_code = 258 + string_table.len() as u16;
+            string_table.insert(string, next_code);
+            if next_code + 1 > 1 << bitcount && bitcount < 12 {
+                bitcount += 1;
+            }
+            if next_code + 1 == 4094 {
+                append_code(&mut bits, 256, bitcount);
+                string_table.clear();
+                bitcount = 9;
+            }
+            curr = vec![byte];
+        }
+        append_code(&mut bits, get_code(&string_table, &curr), bitcount);
+        // The decoder adds an entry for the last code before reading the
+        // EndOfInformation code
+        if 258 + string_table.len() as u16 + 1 > 1 << bitcount && bitcount < 12 {
+            bitcount += 1;
+        }
+        append_code(&mut bits, 257, bitcount);
+
+        bits.chunks(8)
+            .map(|byte| {
+                byte.iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, &bit)| acc | ((bit as u8) << i))
+            })
+            .collect()
+    }
+
+    /// Lengths of noise, from [`noise`] with a seed of 1, whose last codes are
+    /// around where the code width grows to 10, 11 and 12 bits, and where the
+    /// table fills up and is cleared.
+    fn lzw_boundary_lengths() -> impl Iterator<Item = usize> {
+        [244..=264, 760..=780, 1841..=1861, 4069..=4089]
+            .into_iter()
+            .flatten()
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        for len in lzw_boundary_lengths() {
+            assert_round_trip(Lzw, &noise(len, 1), len);
+        }
+        // Long enough to fill the table several times
+        assert_round_trip(Lzw, &noise(20_000, 2), 100);
+        // Long runs make long strings in the table
+        let runs: Vec<u8> = (0..20_000).map(|i| (i / 1000) as u8).collect();
+        assert_round_trip(Lzw, &runs, 100);
+        assert_round_trip(Lzw, &[0; 20_000], 100);
+        assert_round_trip(Lzw, &[5], 1);
+    }
+
+    #[test]
+    fn old_style_lzw_round_trip() {
+        for len in lzw_boundary_lengths() {
+            let data = noise(len, 1);
+            assert_eq!(lzw(&old_style_lzw(&data), len).unwrap(), data);
+        }
+        let data = noise(20_000, 2);
+        assert_eq!(lzw(&old_style_lzw(&data), data.len()).unwrap(), data);
+        let runs: Vec<u8> = (0..20_000).map(|i| (i / 1000) as u8).collect();
+        assert_eq!(lzw(&old_style_lzw(&runs), runs.len()).unwrap(), runs);
+    }
+
+    #[test]
+    fn invalid_lzw_code_is_an_error() {
+        // 300 isn't in the table yet
+        let compressed = pack_9_bit_codes(&[256, 97, 300, 257]);
+        assert!(matches!(
+            lzw(&compressed, 3),
+            Err(DecodeError::InvalidCompressedData)
+        ));
+    }
+
+    #[test]
+    fn overlong_data_is_returned() {
+        // Runs of 4 literal bytes and 3 repeated bytes
+        let packed = [3, 1, 2, 3, 4, -2i8 as u8, 5];
+        assert_eq!(packbits(&packed, 7).unwrap(), [1, 2, 3, 4, 5, 5, 5]);
+        assert_eq!(packbits(&packed, 5).unwrap(), [1, 2, 3, 4, 5, 5, 5]);
+        assert_eq!(packbits(&packed, 2).unwrap(), [1, 2, 3, 4]);
+
+        let compressed = pack_9_bit_codes(&[256, 97, 98, 99, 257]);
+        assert_eq!(lzw(&compressed, 3).unwrap(), b"abc");
+        assert_eq!(lzw(&compressed, 1).unwrap(), b"ab");
+    }
+
+    #[test]
+    fn truncated_packbits_run_is_an_error() {
+        assert!(matches!(
+            packbits(&[3, 1, 2], 4),
+            Err(DecodeError::InvalidCompressedData)
+        ));
+        assert!(matches!(
+            packbits(&[-3i8 as u8], 4),
+            Err(DecodeError::InvalidCompressedData)
+        ));
+    }
+}
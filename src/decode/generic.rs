@@ -0,0 +1,52 @@
+use super::{read_samples_per_pixel, tiff_field, DecodeError, GenericImage, ImageInfo};
+use crate::{bits::BitReader, ifd};
+
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+    white_is_zero: bool,
+) -> Result<GenericImage, DecodeError> {
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?
+        .to_vec();
+    if bits_per_sample.iter().any(|bits| !(1..=32).contains(bits)) {
+        return Err(DecodeError::UnsupportedBitsPerSample(bits_per_sample));
+    }
+    let samples_per_pixel = read_samples_per_pixel(&fields)?;
+    if bits_per_sample.len() != samples_per_pixel as usize {
+        return Err(DecodeError::TiffFieldError);
+    }
+
+    let bits_per_pixel: usize = bits_per_sample.iter().map(|&bits| bits as usize).sum();
+    let bytes_per_row = (info.width * bits_per_pixel).div_ceil(8);
+    let data = info.read_rows(bytes, &bits_per_sample)?;
+
+    let mut samples = Vec::with_capacity(info.width * info.height * bits_per_sample.len());
+    // Each row starts on a new byte
+    for row in data.chunks_exact(bytes_per_row.max(1)) {
+        let mut reader = BitReader::new(row);
+        for _ in 0..info.width {
+            for &bits in &bits_per_sample {
+                let sample = reader
+                    .read_in_byte_order(bits as u8, info.endianness)
+                    .ok_or(DecodeError::CantReadImage)?;
+                samples.push(if white_is_zero {
+                    (u32::MAX >> (32 - bits)) - sample
+                } else {
+                    sample
+                });
+            }
+        }
+    }
+    if samples.is_empty() || samples.len() != info.width * info.height * bits_per_sample.len() {
+        return Err(DecodeError::CantReadImage);
+    }
+
+    Ok(GenericImage {
+        width: info.width,
+        height: info.height,
+        bits_per_sample,
+        samples,
+    })
+}
@@ -0,0 +1,27 @@
+use super::{DecodeError, ImageInfo};
+use crate::{
+    colors::{self, Grayscale16Bit},
+    ifd, Image,
+};
+
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    _fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+    white_is_zero: bool,
+) -> Result<Image<colors::Grayscale16Bit>, DecodeError> {
+    let to_pixel = if white_is_zero {
+        |sample| Grayscale16Bit(u16::MAX - sample)
+    } else {
+        |sample| Grayscale16Bit(sample)
+    };
+
+    let pixels = info
+        .read_rows(bytes, &[16])?
+        .chunks_exact(2)
+        .map(|sample| info.read_u16(sample))
+        .map(to_pixel)
+        .collect();
+
+    Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
+}
@@ -2,9 +2,67 @@ use super::{DecodeError, ImageInfo};
 use crate::{colors, ifd, Image};
 
 pub(crate) fn decode_image(
-    fields: Vec<ifd::Entry>,
+    bytes: &[u8],
+    _fields: Vec<ifd::Entry>,
     info: ImageInfo,
     white_is_zero: bool,
 ) -> Result<Image<colors::Grayscale4Bit>, DecodeError> {
-    todo!()
+    let to_pixel = if white_is_zero {
+        |half_byte| colors::Grayscale4Bit::new(0b1111 - half_byte)
+    } else {
+        colors::Grayscale4Bit::new
+    };
+
+    // Each row starts on a new byte, with the highest order bits first
+    let bytes_per_row = info.width.div_ceil(2);
+    let pixels = info
+        .read_rows(bytes, &[4])?
+        .chunks_exact(bytes_per_row)
+        .flat_map(|row| {
+            row.iter()
+                .flat_map(|byte| [byte >> 4, byte & 0b1111])
+                .take(info.width)
+                .map(to_pixel)
+        })
+        .collect();
+
+    Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use crate::{
+        colors::Grayscale4Bit,
+        encode::{
+            compression::{NoCompression, PackBits},
+            grayscale_4bit::{BlackIsZero, Grayscale4BitImageEncoder, WhiteIsZero},
+            ImageEncoder,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    #[test]
+    fn odd_widths() {
+        for width in [1, 3, 5, 7] {
+            let pixels = (0..width * 3)
+                .map(|i| Grayscale4Bit::new((i * 5 % 16) as u8))
+                .collect();
+            let image = Image::new(pixels, width, 3);
+            for encoded in [
+                Grayscale4BitImageEncoder::<LittleEndian, _, _>::new(
+                    &image,
+                    NoCompression,
+                    BlackIsZero,
+                )
+                .encode(),
+                Grayscale4BitImageEncoder::<BigEndian, _, _>::new(&image, PackBits, WhiteIsZero)
+                    .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
 }
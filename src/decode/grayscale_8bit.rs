@@ -6,35 +6,21 @@ use crate::{
 
 pub(crate) fn decode_image(
     bytes: &[u8],
-    fields: Vec<ifd::Entry>,
+    _fields: Vec<ifd::Entry>,
     info: ImageInfo,
     white_is_zero: bool,
 ) -> Result<Image<colors::Grayscale8Bit>, DecodeError> {
-    let mut pixels = Vec::new();
-    pixels.reserve_exact(info.width * info.height);
-
-    if (info.height != info.rows_per_strip * info.strip_offsets.len())
-        || (info.strip_offsets.len() != info.strip_byte_counts.len())
-    {
-        return Err(DecodeError::CantReadImage);
-    }
-
     let to_pixel = if white_is_zero {
         |byte| Grayscale8Bit(u8::MAX - byte)
     } else {
         |byte| Grayscale8Bit(byte)
     };
 
-    for (offset, strip_byte_count) in info
-        .strip_offsets
+    let pixels = info
+        .read_rows(bytes, &[8])?
         .into_iter()
-        .zip(info.strip_byte_counts.into_iter())
-    {
-        let strip = bytes
-            .get(offset..offset + strip_byte_count)
-            .ok_or(DecodeError::CantReadImage)?;
-        pixels.extend(info.decompressor.decompress(strip).map(to_pixel));
-    }
+        .map(to_pixel)
+        .collect();
 
     Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
 }
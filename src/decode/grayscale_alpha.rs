@@ -0,0 +1,36 @@
+use super::{read_alpha, tiff_field, AlphaImage, DecodeError, ImageInfo};
+use crate::{
+    colors::{self, GrayscaleAlpha},
+    ifd, Image,
+};
+
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+    white_is_zero: bool,
+) -> Result<AlphaImage<colors::GrayscaleAlpha>, DecodeError> {
+    let alpha = read_alpha(&fields)?;
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?;
+    if bits_per_sample != [8, 8] {
+        return Err(DecodeError::UnsupportedBitsPerSample(
+            bits_per_sample.to_vec(),
+        ));
+    }
+    let to_pixel = if white_is_zero {
+        |sample: &[u8]| GrayscaleAlpha::new(u8::MAX - sample[0], sample[1])
+    } else {
+        |sample: &[u8]| GrayscaleAlpha::new(sample[0], sample[1])
+    };
+
+    let pixels = info
+        .read_rows(bytes, bits_per_sample)?
+        .chunks_exact(2)
+        .map(to_pixel)
+        .collect();
+
+    let image =
+        Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)?;
+    Ok(AlphaImage { image, alpha })
+}
@@ -0,0 +1,29 @@
+use super::{read_samples_per_pixel, tiff_field, DecodeError, ImageInfo};
+use crate::{colors, ifd, Image};
+
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+    encoding: colors::LabEncoding,
+) -> Result<Image<colors::Lab>, DecodeError> {
+    let samples_per_pixel = read_samples_per_pixel(&fields)?;
+    if samples_per_pixel != 3 {
+        return Err(DecodeError::UnsupportedSamplesPerPixel(samples_per_pixel));
+    }
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?;
+    if bits_per_sample != [8, 8, 8] {
+        return Err(DecodeError::UnsupportedBitsPerSample(
+            bits_per_sample.to_vec(),
+        ));
+    }
+
+    let pixels = info
+        .read_rows(bytes, bits_per_sample)?
+        .chunks_exact(3)
+        .map(|sample| encoding.decode_samples([sample[0], sample[1], sample[2]]))
+        .collect();
+
+    Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
+}
@@ -1,10 +1,19 @@
 mod bilevel;
+mod cmyk;
 pub mod compression;
+mod generic;
+mod grayscale_16bit;
 mod grayscale_4bit;
 mod grayscale_8bit;
+mod grayscale_alpha;
+mod lab;
 mod palette_color;
 mod rgb;
-mod tiff_field;
+mod rgb16;
+mod rgba;
+mod sample;
+pub(crate) mod tiff_field;
+mod ycbcr;
 
 use std::{
     borrow::BorrowMut,
@@ -14,31 +23,53 @@ use std::{
 
 use byteordered::{Endian, Endianness};
 
-use crate::{colors, ifd, types::URational, Image};
+use crate::{
+    bits::{BitReader, BitWriter},
+    colors, ifd,
+    metadata::Metadata,
+    resolution::Resolution,
+    Image,
+};
 
 use self::compression::{sealed::DecompressionImpl, tag_to_decompressor};
 
-#[allow(unused)]
+pub use crate::ifd::tags::YCbCrPositioning;
+
 pub fn decode_images(bytes: &[u8]) -> Result<DecodeResult, DecodeError> {
-    // Read endianness from header and check magic number
-    let endianness = match bytes {
+    decode_images_with_options(bytes, DecodeOptions::default())
+}
+
+#[allow(unused)]
+pub fn decode_images_with_options(
+    bytes: &[u8],
+    options: DecodeOptions,
+) -> Result<DecodeResult, DecodeError> {
+    // Read endianness and format from header and check magic number
+    let (endianness, format) = match bytes {
         // II
-        [73, 73, 42, 00, ..] => Endianness::Little,
+        [73, 73, 42, 00, ..] => (Endianness::Little, ifd::Format::Classic),
+        [73, 73, 43, 00, 8, 0, 0, 0, ..] => (Endianness::Little, ifd::Format::BigTiff),
         // MM
-        [77, 77, 00, 42, ..] => Endianness::Big,
+        [77, 77, 00, 42, ..] => (Endianness::Big, ifd::Format::Classic),
+        [77, 77, 00, 43, 0, 8, 0, 0, ..] => (Endianness::Big, ifd::Format::BigTiff),
         _ => return Err(DecodeError::InvalidFiletype),
     };
 
+    // The offset of the first IFD follows the magic number, and for BigTIFF
+    // the offset byte size and a reserved short
+    let first_ifd_offset_inx = match format {
+        ifd::Format::Classic => 4,
+        ifd::Format::BigTiff => 8,
+    };
     let mut ifd_index = bytes
-        .get(4..8)
+        .get(first_ifd_offset_inx..first_ifd_offset_inx + format.offset_len())
         .ok_or(DecodeError::InvalidFiletype)
         .and_then(|slice| {
-            endianness
-                .read_u32(slice)
+            tiff_field::read_offset(endianness, format, slice)
                 .map_err(|_| DecodeError::InvalidFiletype)
-        })? as usize;
+        })?;
 
-    let mut images = Vec::new();
+    let mut pages = Vec::new();
     let mut errors = Vec::new();
 
     let mut ifd_indices = Vec::new();
@@ -53,37 +84,354 @@ pub fn decode_images(bytes: &[u8]) -> Result<DecodeResult, DecodeError> {
         ifd_indices.push(ifd_index);
 
         // TODO: Include any any successfully decoded images in return
-        let ifd = Ifd::new(bytes, ifd_index, endianness)?;
+        let ifd = Ifd::new(bytes, ifd_index, endianness, format)?;
         ifd_index = ifd.next_ifd_index;
 
-        match decode_image(bytes, endianness, ifd) {
-            Ok((image, field_errors)) => {
-                images.push(image);
+        match decode_image(bytes, endianness, ifd, options) {
+            Ok((page, field_errors)) => {
+                pages.push(page);
                 errors.extend(field_errors)
             }
             Err(err) => errors.push(err),
         }
     }
 
-    Ok(DecodeResult { images, errors })
+    Ok(DecodeResult { pages, errors })
+}
+
+/// Options for how images are decoded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DecodeOptions {
+    ycbcr_planes: bool,
+}
+
+impl DecodeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether YCbCr images are returned as their raw, possibly
+    /// subsampled, planes instead of being converted to RGB.
+    pub fn with_ycbcr_planes(mut self, ycbcr_planes: bool) -> Self {
+        self.ycbcr_planes = ycbcr_planes;
+        self
+    }
 }
 
 pub struct DecodeResult {
-    pub images: Vec<DecodedImage>,
+    pub pages: Vec<DecodedPage>,
     pub errors: Vec<DecodeError>,
 }
 
+/// An image decoded from an image file directory, along with the entries of
+/// the directory that aren't otherwise read.
+pub struct DecodedPage {
+    pub image: DecodedImage,
+    pub metadata: Metadata,
+    pub resolution: Resolution,
+    /// Entries with tags this crate doesn't know, such as private tags, which
+    /// can be written back using [`crate::encode::ImageEncoder::with_raw_entries`].
+    ///
+    /// Entries that point to other data in the file, such as the Exif and GPS
+    /// IFDs or SubIFDs, are not included, as the offsets would be wrong in
+    /// any other file.
+    pub raw_entries: Vec<ifd::RawEntry>,
+}
+
 pub enum DecodedImage {
     BilevelImage(Image<colors::Bilevel>),
     Grayscale8BitImage(Image<colors::Grayscale8Bit>),
     Grayscale4BitImage(Image<colors::Grayscale4Bit>),
+    Grayscale16BitImage(Image<colors::Grayscale16Bit>),
     RGBImage(Image<colors::RGB>),
+    RGB16Image(Image<colors::RGB16>),
+    RGBAImage(AlphaImage<colors::RGBA>),
+    GrayscaleAlphaImage(AlphaImage<colors::GrayscaleAlpha>),
+    CMYKImage(CMYKImage),
+    LabImage(Image<colors::Lab>),
+    GrayscaleF16Image(FloatImage<colors::GrayscaleF16>),
+    GrayscaleF32Image(FloatImage<colors::GrayscaleF32>),
+    GrayscaleF64Image(FloatImage<colors::GrayscaleF64>),
+    RGBF16Image(FloatImage<colors::RGBF16>),
+    RGBF32Image(FloatImage<colors::RGBF32>),
+    RGBF64Image(FloatImage<colors::RGBF64>),
+    GrayscaleI8Image(Image<colors::GrayscaleI8>),
+    GrayscaleI16Image(Image<colors::GrayscaleI16>),
+    GrayscaleI32Image(Image<colors::GrayscaleI32>),
+    RGBI8Image(Image<colors::RGBI8>),
+    RGBI16Image(Image<colors::RGBI16>),
+    RGBI32Image(Image<colors::RGBI32>),
+    /// Only returned when [`DecodeOptions::with_ycbcr_planes`] is set, YCbCr
+    /// images are otherwise converted to [`DecodedImage::RGBImage`].
+    YCbCrImage(YCbCrImage),
+    PaletteImage(PaletteImage),
+    /// Grayscale and RGB images with bit depths that don't have a dedicated
+    /// color type.
+    GenericImage(GenericImage),
+}
+
+/// A decoded palette-color image that owns the color map its indices refer
+/// to.
+pub struct PaletteImage {
+    color_map: colors::ColorMap,
+    /// Indices into the color map arranged left to right, then top to bottom
+    indices: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl PaletteImage {
+    /// Returns the image with pixels referencing [`Self::color_map`], which
+    /// can be re-encoded using
+    /// [`crate::encode::palette_color::PaletteColorImageEncoder`].
+    pub fn image(&self) -> Image<colors::PaletteColor<'_>> {
+        self.color_map
+            .try_new_indexed_image(&self.indices, self.width, self.height)
+            .unwrap()
+    }
+
+    pub fn color_map(&self) -> &colors::ColorMap {
+        &self.color_map
+    }
+
+    /// Indices into the color map arranged left to right, then top to bottom.
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+/// A decoded separated image using cyan, magenta, yellow and black inks.
+pub struct CMYKImage {
+    image: Image<colors::CMYK>,
+    ink_names: Option<Vec<String>>,
+}
+
+impl CMYKImage {
+    pub fn image(&self) -> &Image<colors::CMYK> {
+        &self.image
+    }
+
+    /// The names of the cyan, magenta, yellow and black inks, if the file
+    /// names them.
+    pub fn ink_names(&self) -> Option<&[String]> {
+        self.ink_names.as_deref()
+    }
+
+    pub fn into_image(self) -> Image<colors::CMYK> {
+        self.image
+    }
+}
+
+/// A decoded image of unsigned integer samples with any bit depth from 1 to
+/// 32.
+///
+/// Grayscale images have a single sample per pixel with 0 as black, and RGB
+/// images have red, green and blue samples.
+pub struct GenericImage {
+    width: usize,
+    height: usize,
+    bits_per_sample: Vec<u16>,
+    /// Samples of each pixel arranged left to right, then top to bottom
+    samples: Vec<u32>,
+}
+
+impl GenericImage {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn samples_per_pixel(&self) -> usize {
+        self.bits_per_sample.len()
+    }
+
+    /// The bit depth of each sample in a pixel.
+    pub fn bits_per_sample(&self) -> &[u16] {
+        &self.bits_per_sample
+    }
+
+    /// Samples of each pixel arranged left to right, then top to bottom.
+    pub fn samples(&self) -> &[u32] {
+        &self.samples
+    }
+
+    /// Returns the image with `N` samples per pixel, which can be re-encoded
+    /// using [`crate::encode::generic`], or `None` if the image has a
+    /// different number of samples per pixel.
+    pub fn image<const N: usize>(&self) -> Option<Image<colors::Samples<N>>> {
+        if N != self.samples_per_pixel() {
+            return None;
+        }
+        let pixels = self
+            .samples
+            .chunks_exact(N)
+            .map(|samples| colors::Samples(samples.try_into().unwrap()))
+            .collect();
+        Image::try_new(pixels, self.width, self.height).ok()
+    }
+}
+
+/// A decoded image with floating point samples, along with the range of
+/// sample values if the file gives it.
+pub struct FloatImage<C: colors::Color> {
+    image: Image<C>,
+    min_sample_value: Option<Vec<f64>>,
+    max_sample_value: Option<Vec<f64>>,
+}
+
+impl<C: colors::Color> FloatImage<C> {
+    /// Reads the range of sample values from the fields.
+    fn new(image: Image<C>, fields: &[ifd::Entry]) -> Result<Self, DecodeError> {
+        let min_sample_value = tiff_field::find(fields, ifd::Tag::SMinSampleValue)
+            .map(tiff_field::read_f64s)
+            .transpose()?;
+        let max_sample_value = tiff_field::find(fields, ifd::Tag::SMaxSampleValue)
+            .map(tiff_field::read_f64s)
+            .transpose()?;
+        Ok(Self {
+            image,
+            min_sample_value,
+            max_sample_value,
+        })
+    }
+
+    pub fn image(&self) -> &Image<C> {
+        &self.image
+    }
+
+    /// The minimum value of each sample, or of all samples if there is only
+    /// one value.
+    pub fn min_sample_value(&self) -> Option<&[f64]> {
+        self.min_sample_value.as_deref()
+    }
+
+    /// The maximum value of each sample, or of all samples if there is only
+    /// one value.
+    pub fn max_sample_value(&self) -> Option<&[f64]> {
+        self.max_sample_value.as_deref()
+    }
+
+    pub fn into_image(self) -> Image<C> {
+        self.image
+    }
+}
+
+/// The raw planes of a decoded YCbCr image.
+pub struct YCbCrImage {
+    width: usize,
+    height: usize,
+    subsampling: (u16, u16),
+    positioning: YCbCrPositioning,
+    coefficients: [f32; 3],
+    reference_black_white: [f32; 6],
+    luma: Vec<u8>,
+    cb: Vec<u8>,
+    cr: Vec<u8>,
+}
+
+impl YCbCrImage {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// How many luma samples each chroma sample covers horizontally and
+    /// vertically.
+    pub fn subsampling(&self) -> (u16, u16) {
+        self.subsampling
+    }
+
+    pub fn positioning(&self) -> YCbCrPositioning {
+        self.positioning
+    }
+
+    /// The red, green and blue luma coefficients.
+    pub fn coefficients(&self) -> [f32; 3] {
+        self.coefficients
+    }
+
+    /// The black and white reference points of the Y, Cb and Cr samples.
+    pub fn reference_black_white(&self) -> [f32; 6] {
+        self.reference_black_white
+    }
+
+    /// Luma samples arranged left to right, then top to bottom, with one
+    /// sample per pixel.
+    pub fn luma(&self) -> &[u8] {
+        &self.luma
+    }
+
+    /// Blue-difference chroma samples arranged left to right, then top to
+    /// bottom, with one sample per subsampled block of pixels.
+    pub fn cb(&self) -> &[u8] {
+        &self.cb
+    }
+
+    /// Red-difference chroma samples arranged left to right, then top to
+    /// bottom, with one sample per subsampled block of pixels.
+    pub fn cr(&self) -> &[u8] {
+        &self.cr
+    }
+
+    /// Converts the image to RGB, using the chroma samples of each
+    /// subsampled block for every pixel in it.
+    pub fn to_rgb(&self) -> Image<colors::RGB> {
+        ycbcr::to_rgb(self)
+    }
+}
+
+/// A decoded image with an alpha sample, along with whether the other samples
+/// have been premultiplied by it.
+pub struct AlphaImage<C: colors::AlphaColor> {
+    image: Image<C>,
+    alpha: colors::Alpha,
+}
+
+impl<C: colors::AlphaColor<ViewAs = C>> AlphaImage<C> {
+    /// Returns the image as stored in the file.
+    pub fn image(&self) -> &Image<C> {
+        &self.image
+    }
+
+    /// Whether the colors of [`Self::image`] have associated alpha.
+    pub fn alpha(&self) -> colors::Alpha {
+        self.alpha
+    }
+
+    /// Returns the image, converting its colors to the given kind of alpha
+    /// if they are stored with the other kind.
+    pub fn into_image(self, alpha: colors::Alpha) -> Image<C> {
+        let convert = match (self.alpha, alpha) {
+            (colors::Alpha::Unassociated, colors::Alpha::Associated) => C::associate,
+            (colors::Alpha::Associated, colors::Alpha::Unassociated) => C::unassociate,
+            _ => return self.image,
+        };
+        let (width, height) = (self.image.width(), self.image.height());
+        let pixels = Image::into_pixels(self.image)
+            .into_iter()
+            .map(convert)
+            .collect();
+        Image::new(pixels, width, height)
+    }
 }
 
 #[derive(Debug)]
 pub enum DecodeError {
     InvalidFiletype,
-    UnknownFieldTag(u16),
     UnknownFieldType(u16),
     CantReadField,
     MissingRequiredField,
@@ -93,6 +441,45 @@ pub enum DecodeError {
     InvalidImageFieldDirectory(usize),
     UnsupportedCompressionType,
     // UnsupportedCompressionType(ifd::tags::Compression),
+    /// The compressed image data is truncated or otherwise invalid.
+    InvalidCompressedData,
+    /// A strip decompressed to fewer bytes than its rows take up.
+    ShortStrip {
+        strip: usize,
+        expected_len: usize,
+        len: usize,
+    },
+    /// A strip decompressed to more bytes than its rows take up. As
+    /// decompression stops once past the expected length, `len` may be less
+    /// than the full length of compressed data.
+    OverlongStrip {
+        strip: usize,
+        expected_len: usize,
+        len: usize,
+    },
+    /// A tile decompressed to fewer bytes than a whole tile takes up.
+    ShortTile {
+        tile: usize,
+        expected_len: usize,
+        len: usize,
+    },
+    /// A tile decompressed to more bytes than a whole tile takes up. As
+    /// decompression stops once past the expected length, `len` may be less
+    /// than the full length of compressed data.
+    OverlongTile {
+        tile: usize,
+        expected_len: usize,
+        len: usize,
+    },
+    UnsupportedBitsPerSample(Vec<u16>),
+    UnsupportedSamplesPerPixel(u16),
+    /// The extra samples of an image are not alpha.
+    UnsupportedExtraSamples,
+    /// A separated image uses inks other than CMYK.
+    UnsupportedInkSet,
+    /// The sample format is not supported for the photometric
+    /// interpretation, bits per sample or samples per pixel of an image.
+    UnsupportedSampleFormat,
     CantReadImage,
     // InvalidTypeForTag {
     //     tag: ifd::Tag,
@@ -108,13 +495,18 @@ pub enum DecodeError {
     // },
 }
 
-// TODO: Include resolution unit of image
 fn decode_image(
     bytes: &[u8],
     endianness: Endianness,
     ifd: Ifd<'_>,
-) -> Result<(DecodedImage, Vec<DecodeError>), DecodeError> {
-    let (mut fields, field_errors) = read_image_field_directory(bytes, endianness, ifd)?;
+    options: DecodeOptions,
+) -> Result<(DecodedPage, Vec<DecodeError>), DecodeError> {
+    let ImageFieldDirectory {
+        mut fields,
+        raw_entries,
+        mut field_errors,
+    } = read_image_field_directory(bytes, endianness, ifd)?;
+    let metadata = read_metadata(&mut fields, &mut field_errors);
 
     let width = tiff_field::find_required(&fields, ifd::Tag::ImageWidth)
         .and_then(tiff_field::read_single)?;
@@ -135,13 +527,50 @@ fn decode_image(
             ifd::tags::PhotometricInterpretation::from_repr(photo_interp)
                 .ok_or(DecodeError::TiffFieldError)
         })?;
-    let strip_offsets = tiff_field::take_required(&mut fields, ifd::Tag::StripOffsets)
-        .and_then(tiff_field::as_usizes)?;
-    let rows_per_strip = tiff_field::find(&fields, ifd::Tag::RowsPerStrip)
-        .map(tiff_field::read_single)
-        .unwrap_or(Ok(u32::MAX))? as usize;
-    let strip_byte_counts = tiff_field::take_required(&mut fields, ifd::Tag::StripByteCounts)
-        .and_then(tiff_field::as_usizes)?;
+    let sample_format = match tiff_field::find(&fields, ifd::Tag::SampleFormat) {
+        Some(values) => tiff_field::read_shorts(values).and_then(|sample_formats| {
+            // Every sample must have the same format
+            match sample_formats {
+                [sample_format, rest @ ..] if rest.iter().all(|rest| rest == sample_format) => {
+                    // TODO: Use DecodeError::InvalidTagValues
+                    ifd::tags::SampleFormat::from_repr(*sample_format)
+                        .ok_or(DecodeError::TiffFieldError)
+                }
+                _ => Err(DecodeError::UnsupportedSampleFormat),
+            }
+        })?,
+        None => Default::default(),
+    };
+    let data_layout = if tiff_field::find(&fields, ifd::Tag::TileWidth).is_some() {
+        DataLayout::Tiles {
+            tile_width: tiff_field::find_required(&fields, ifd::Tag::TileWidth)
+                .and_then(tiff_field::read_single)? as usize,
+            tile_length: tiff_field::find_required(&fields, ifd::Tag::TileLength)
+                .and_then(tiff_field::read_single)? as usize,
+            offsets: tiff_field::take_required(&mut fields, ifd::Tag::TileOffsets)
+                .and_then(tiff_field::as_usizes)?,
+            byte_counts: tiff_field::take_required(&mut fields, ifd::Tag::TileByteCounts)
+                .and_then(tiff_field::as_usizes)?,
+        }
+    } else {
+        DataLayout::Strips {
+            offsets: tiff_field::take_required(&mut fields, ifd::Tag::StripOffsets)
+                .and_then(tiff_field::as_usizes)?,
+            rows_per_strip: tiff_field::find(&fields, ifd::Tag::RowsPerStrip)
+                .map(tiff_field::read_single)
+                .unwrap_or(Ok(u32::MAX))? as usize,
+            byte_counts: tiff_field::take_required(&mut fields, ifd::Tag::StripByteCounts)
+                .and_then(tiff_field::as_usizes)?,
+        }
+    };
+    let planar_configuration = match tiff_field::find(&fields, ifd::Tag::PlanarConfiguration) {
+        Some(values) => tiff_field::read_single_short(values).and_then(|planar_configuration| {
+            // TODO: Use DecodeError::InvalidTagValues
+            ifd::tags::PlanarConfiguration::from_repr(planar_configuration)
+                .ok_or(DecodeError::TiffFieldError)
+        })?,
+        None => Default::default(),
+    };
     let x_resolution = tiff_field::find_required(&fields, ifd::Tag::XResolution)
         .and_then(tiff_field::read_single_rational)?;
     let y_resolution = tiff_field::find_required(&fields, ifd::Tag::YResolution)
@@ -154,44 +583,343 @@ fn decode_image(
         None => Default::default(),
     };
 
+    let resolution = Resolution::new(x_resolution, y_resolution, resolution_unit);
+
     let info = ImageInfo {
+        endianness,
         width: width.try_into().unwrap(),
         height: height.try_into().unwrap(),
         decompressor,
-        strip_offsets,
-        rows_per_strip,
-        strip_byte_counts,
-        x_resolution,
-        y_resolution,
-        resolution_unit,
+        data_layout,
+        planar_configuration,
     };
 
-    let image = match photo_interp {
-        ifd::tags::PhotometricInterpretation::WhiteIsZero
-        | ifd::tags::PhotometricInterpretation::BlackIsZero => {
-            decode_bw_image(bytes, fields, info, photo_interp)
-        }
-        ifd::tags::PhotometricInterpretation::RGB => {
-            rgb::decode_image(fields, info).map(DecodedImage::RGBImage)
-        }
-        ifd::tags::PhotometricInterpretation::PaletteColor => {
-            palette_color::decode_image(fields, info).map(DecodedImage::RGBImage)
+    let image = match sample_format {
+        ifd::tags::SampleFormat::Float => decode_float_image(bytes, fields, info, photo_interp),
+        ifd::tags::SampleFormat::Signed => decode_signed_image(bytes, fields, info, photo_interp),
+        ifd::tags::SampleFormat::Unsigned | ifd::tags::SampleFormat::Undefined => {
+            match photo_interp {
+                ifd::tags::PhotometricInterpretation::WhiteIsZero
+                | ifd::tags::PhotometricInterpretation::BlackIsZero => {
+                    match read_samples_per_pixel(&fields)? {
+                        2 => grayscale_alpha::decode_image(
+                            bytes,
+                            fields,
+                            info,
+                            photo_interp == ifd::tags::PhotometricInterpretation::WhiteIsZero,
+                        )
+                        .map(DecodedImage::GrayscaleAlphaImage),
+                        _ => decode_bw_image(bytes, fields, info, photo_interp),
+                    }
+                }
+                ifd::tags::PhotometricInterpretation::RGB => decode_rgb_image(bytes, fields, info),
+                ifd::tags::PhotometricInterpretation::PaletteColor => {
+                    palette_color::decode_image(bytes, fields, info).map(DecodedImage::PaletteImage)
+                }
+                ifd::tags::PhotometricInterpretation::Separated => {
+                    cmyk::decode_image(bytes, fields, info).map(DecodedImage::CMYKImage)
+                }
+                ifd::tags::PhotometricInterpretation::YCbCr => {
+                    ycbcr::decode_image(bytes, fields, info).map(|image| {
+                        if options.ycbcr_planes {
+                            DecodedImage::YCbCrImage(image)
+                        } else {
+                            DecodedImage::RGBImage(image.to_rgb())
+                        }
+                    })
+                }
+                ifd::tags::PhotometricInterpretation::CIELab => {
+                    lab::decode_image(bytes, fields, info, colors::LabEncoding::Cie)
+                        .map(DecodedImage::LabImage)
+                }
+                ifd::tags::PhotometricInterpretation::ICCLab => {
+                    lab::decode_image(bytes, fields, info, colors::LabEncoding::Icc)
+                        .map(DecodedImage::LabImage)
+                }
+                ifd::tags::PhotometricInterpretation::ITULab => {
+                    lab::decode_image(bytes, fields, info, colors::LabEncoding::Itu)
+                        .map(DecodedImage::LabImage)
+                }
+            }
         }
     }?;
 
-    Ok((image, field_errors))
+    Ok((
+        DecodedPage {
+            image,
+            metadata,
+            resolution,
+            raw_entries,
+        },
+        field_errors,
+    ))
 }
 
 pub(crate) struct ImageInfo {
+    endianness: Endianness,
     width: usize,
     height: usize,
     decompressor: Box<dyn DecompressionImpl>,
-    strip_offsets: Vec<usize>,
-    rows_per_strip: usize,
-    strip_byte_counts: Vec<usize>,
-    x_resolution: URational,
-    y_resolution: URational,
-    resolution_unit: ifd::tags::ResolutionUnit,
+    data_layout: DataLayout,
+    planar_configuration: ifd::tags::PlanarConfiguration,
+}
+
+/// How the image data is split up in the file.
+pub(crate) enum DataLayout {
+    Strips {
+        offsets: Vec<usize>,
+        rows_per_strip: usize,
+        byte_counts: Vec<usize>,
+    },
+    Tiles {
+        tile_width: usize,
+        tile_length: usize,
+        offsets: Vec<usize>,
+        byte_counts: Vec<usize>,
+    },
+}
+
+impl ImageInfo {
+    /// Reads a 16-bit sample stored in the byte order of the file.
+    fn read_u16(&self, sample: &[u8]) -> u16 {
+        self.endianness.read_u16(sample).unwrap()
+    }
+
+    /// Reads and decompresses the image data, returning the rows of the image
+    /// back to back with the samples of each pixel stored together. Each row
+    /// starts on a new byte.
+    fn read_rows(&self, bytes: &[u8], bits_per_sample: &[u16]) -> Result<Vec<u8>, DecodeError> {
+        let bits_per_pixel = bits_per_sample.iter().map(|&bits| bits as usize).sum();
+        match self.planar_configuration {
+            ifd::tags::PlanarConfiguration::Planar if bits_per_sample.len() > 1 => {
+                let planes = bits_per_sample
+                    .iter()
+                    .enumerate()
+                    .map(|(plane, &bits)| {
+                        self.read_plane(bytes, bits as usize, plane, bits_per_sample.len(), (1, 1))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut data = Vec::with_capacity(planes.iter().map(Vec::len).sum());
+                if bits_per_sample.iter().all(|bits| bits.is_multiple_of(8)) {
+                    for pixel in 0..self.width * self.height {
+                        for (plane, &bits) in planes.iter().zip(bits_per_sample) {
+                            let sample_len = bits as usize / 8;
+                            data.extend_from_slice(
+                                &plane[pixel * sample_len..(pixel + 1) * sample_len],
+                            );
+                        }
+                    }
+                } else {
+                    // Samples that don't take up whole bytes are unpacked one
+                    // at a time, which only works up to 32 bits
+                    if bits_per_sample.iter().any(|&bits| bits == 0 || bits > 32) {
+                        return Err(DecodeError::UnsupportedBitsPerSample(
+                            bits_per_sample.to_vec(),
+                        ));
+                    }
+                    // Each row of each plane starts on a new byte
+                    let mut plane_rows: Vec<_> = planes
+                        .iter()
+                        .zip(bits_per_sample)
+                        .map(|(plane, &bits)| {
+                            plane.chunks_exact((self.width * bits as usize).div_ceil(8))
+                        })
+                        .collect();
+                    for _ in 0..self.height {
+                        let mut readers = plane_rows
+                            .iter_mut()
+                            .map(|rows| rows.next().map(BitReader::new))
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or(DecodeError::CantReadImage)?;
+                        let mut writer = BitWriter::new();
+                        for _ in 0..self.width {
+                            for (reader, &bits) in readers.iter_mut().zip(bits_per_sample) {
+                                let sample =
+                                    reader.read(bits as u8).ok_or(DecodeError::CantReadImage)?;
+                                writer.write(sample, bits as u8);
+                            }
+                        }
+                        data.extend(writer.into_bytes());
+                    }
+                }
+                Ok(data)
+            }
+            _ => self.read_plane(bytes, bits_per_pixel, 0, 1, (1, 1)),
+        }
+    }
+
+    /// Reads and decompresses a single plane of the image data, returning the
+    /// rows of the plane back to back. Each row starts on a new byte.
+    ///
+    /// Each "pixel" of a plane subsampled by `(horizontal, vertical)` covers
+    /// that many pixels of the image, so the plane has fewer, shorter rows.
+    fn read_plane(
+        &self,
+        bytes: &[u8],
+        bits_per_pixel: usize,
+        plane: usize,
+        plane_count: usize,
+        (horizontal, vertical): (usize, usize),
+    ) -> Result<Vec<u8>, DecodeError> {
+        // The strips or tiles for each plane are stored one plane after another
+        fn plane_chunks(
+            chunks: &[usize],
+            plane: usize,
+            plane_count: usize,
+        ) -> Result<&[usize], DecodeError> {
+            // Every plane must have the same number of strips or tiles
+            if !chunks.len().is_multiple_of(plane_count) {
+                return Err(DecodeError::TiffFieldError);
+            }
+            let chunks_per_plane = chunks.len() / plane_count;
+            Ok(&chunks[plane * chunks_per_plane..(plane + 1) * chunks_per_plane])
+        }
+        let width = self.width.div_ceil(horizontal);
+        let height = self.height.div_ceil(vertical);
+        match &self.data_layout {
+            DataLayout::Strips {
+                offsets,
+                rows_per_strip,
+                byte_counts,
+            } => self.read_strips(
+                bytes,
+                (width * bits_per_pixel).div_ceil(8),
+                height,
+                plane_chunks(offsets, plane, plane_count)?,
+                rows_per_strip.div_ceil(vertical),
+                plane_chunks(byte_counts, plane, plane_count)?,
+            ),
+            DataLayout::Tiles {
+                tile_width,
+                tile_length,
+                offsets,
+                byte_counts,
+            } => {
+                // Tiles must hold whole subsampled pixels
+                if !tile_width.is_multiple_of(horizontal) || !tile_length.is_multiple_of(vertical) {
+                    return Err(DecodeError::TiffFieldError);
+                }
+                self.read_tiles(
+                    bytes,
+                    bits_per_pixel,
+                    (width, height),
+                    (tile_width / horizontal, tile_length / vertical),
+                    plane_chunks(offsets, plane, plane_count)?,
+                    plane_chunks(byte_counts, plane, plane_count)?,
+                )
+            }
+        }
+    }
+
+    fn read_strips(
+        &self,
+        bytes: &[u8],
+        bytes_per_row: usize,
+        height: usize,
+        offsets: &[usize],
+        rows_per_strip: usize,
+        byte_counts: &[usize],
+    ) -> Result<Vec<u8>, DecodeError> {
+        // The last strip may have fewer rows than the others
+        let rows_per_strip = rows_per_strip.clamp(1, height.max(1));
+        let strip_count = height.div_ceil(rows_per_strip);
+        if (offsets.len() != strip_count) || (offsets.len() != byte_counts.len()) {
+            return Err(DecodeError::CantReadImage);
+        }
+
+        let mut data = Vec::with_capacity(bytes_per_row * height);
+        for (strip, (&offset, &strip_byte_count)) in offsets.iter().zip(byte_counts).enumerate() {
+            let rows = rows_per_strip.min(height - strip * rows_per_strip);
+            let expected_len = bytes_per_row * rows;
+            let strip_bytes = offset
+                .checked_add(strip_byte_count)
+                .and_then(|end| bytes.get(offset..end))
+                .ok_or(DecodeError::CantReadImage)?;
+            let decompressed = self.decompressor.decompress(strip_bytes, expected_len)?;
+            match decompressed.len() {
+                len if len < expected_len => {
+                    return Err(DecodeError::ShortStrip {
+                        strip,
+                        expected_len,
+                        len,
+                    })
+                }
+                len if len > expected_len => {
+                    return Err(DecodeError::OverlongStrip {
+                        strip,
+                        expected_len,
+                        len,
+                    })
+                }
+                _ => data.extend(decompressed),
+            }
+        }
+        Ok(data)
+    }
+
+    fn read_tiles(
+        &self,
+        bytes: &[u8],
+        bits_per_pixel: usize,
+        (width, height): (usize, usize),
+        (tile_width, tile_length): (usize, usize),
+        offsets: &[usize],
+        byte_counts: &[usize],
+    ) -> Result<Vec<u8>, DecodeError> {
+        // Tiles must start on a byte within each row
+        if tile_width == 0 || tile_length == 0 || !(tile_width * bits_per_pixel).is_multiple_of(8) {
+            return Err(DecodeError::TiffFieldError);
+        }
+        let tiles_across = width.div_ceil(tile_width);
+        let tiles_down = height.div_ceil(tile_length);
+        if (offsets.len() != tiles_across * tiles_down) || (offsets.len() != byte_counts.len()) {
+            return Err(DecodeError::CantReadImage);
+        }
+
+        let bytes_per_row = (width * bits_per_pixel).div_ceil(8);
+        let bytes_per_tile_row = tile_width * bits_per_pixel / 8;
+        // Every tile is padded to the full tile size
+        let expected_len = bytes_per_tile_row * tile_length;
+        let mut data = vec![0; bytes_per_row * height];
+        for (tile, (&offset, &tile_byte_count)) in offsets.iter().zip(byte_counts).enumerate() {
+            let tile_bytes = offset
+                .checked_add(tile_byte_count)
+                .and_then(|end| bytes.get(offset..end))
+                .ok_or(DecodeError::CantReadImage)?;
+            let decompressed = self.decompressor.decompress(tile_bytes, expected_len)?;
+            match decompressed.len() {
+                len if len < expected_len => {
+                    return Err(DecodeError::ShortTile {
+                        tile,
+                        expected_len,
+                        len,
+                    })
+                }
+                len if len > expected_len => {
+                    return Err(DecodeError::OverlongTile {
+                        tile,
+                        expected_len,
+                        len,
+                    })
+                }
+                _ => (),
+            }
+
+            // Copy the part of each tile row inside the image, cropping any
+            // padding past the right and bottom edges
+            let first_row = (tile / tiles_across) * tile_length;
+            let row_start = (tile % tiles_across) * bytes_per_tile_row;
+            let copy_len = bytes_per_tile_row.min(bytes_per_row - row_start);
+            for (row, tile_row) in
+                (first_row..height).zip(decompressed.chunks_exact(bytes_per_tile_row))
+            {
+                let start = row * bytes_per_row + row_start;
+                data[start..start + copy_len].copy_from_slice(&tile_row[..copy_len]);
+            }
+        }
+        Ok(data)
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -199,35 +927,49 @@ struct Ifd<'a> {
     ifd_bytes: &'a [u8],
     index: usize,
     next_ifd_index: usize,
+    format: ifd::Format,
 }
 impl<'a> Ifd<'a> {
-    fn new(bytes: &'a [u8], ifd_index: usize, endianness: Endianness) -> Result<Self, DecodeError> {
-        let entry_count = bytes
-            .get(ifd_index..ifd_index + ifd::ENTRY_COUNT_LEN)
+    fn new(
+        bytes: &'a [u8],
+        ifd_index: usize,
+        endianness: Endianness,
+        format: ifd::Format,
+    ) -> Result<Self, DecodeError> {
+        let entry_count_bytes = bytes
+            .get(ifd_index..ifd_index + format.entry_count_len())
+            .ok_or(DecodeError::InvalidImageFieldDirectory(ifd_index))?;
+        let entry_count = match format {
+            ifd::Format::Classic => endianness.read_u16(entry_count_bytes).map(u64::from),
+            ifd::Format::BigTiff => endianness.read_u64(entry_count_bytes),
+        }
+        .map_err(|_| DecodeError::InvalidImageFieldDirectory(ifd_index))?
+        .try_into()
+        .map_err(|_| DecodeError::InvalidImageFieldDirectory(ifd_index))?;
+        let entries_start_inx = ifd_index + format.entry_count_len();
+        let entries_end_inx = usize::checked_mul(entry_count, format.entry_len())
+            .and_then(|entries_len| entries_start_inx.checked_add(entries_len))
+            .ok_or(DecodeError::InvalidImageFieldDirectory(ifd_index))?;
+        let ifd_bytes = bytes
+            .get(entries_start_inx..entries_end_inx)
+            .ok_or(DecodeError::InvalidImageFieldDirectory(ifd_index))?;
+        let next_ifd_index = bytes
+            .get(entries_end_inx..entries_end_inx + format.offset_len())
             .ok_or(DecodeError::InvalidImageFieldDirectory(ifd_index))
             .and_then(|slice| {
-                endianness
-                    .read_u16(slice)
+                tiff_field::read_offset(endianness, format, slice)
                     .map_err(|_| DecodeError::InvalidImageFieldDirectory(ifd_index))
-            })? as usize;
-        let entries_start_inx = ifd_index + ifd::ENTRY_COUNT_LEN;
-        let entries_end_inx = entries_start_inx + entry_count * ifd::Entry::LEN;
-        let entries_range = entries_start_inx..entries_end_inx;
-        let ifd_bytes = &bytes[entries_range];
-        let next_ifd_index = endianness
-            .read_u32(&bytes[entries_end_inx..entries_end_inx + 4])
-            .map_err(|_| DecodeError::InvalidImageFieldDirectory(ifd_index))?
-            .try_into()
-            .unwrap();
+            })?;
         Ok(Self {
             ifd_bytes,
             index: ifd_index,
             next_ifd_index,
+            format,
         })
     }
 
     fn fields(&self) -> slice::ChunksExact<'_, u8> {
-        self.ifd_bytes.chunks_exact(ifd::Entry::LEN)
+        self.ifd_bytes.chunks_exact(self.format.entry_len())
     }
 }
 
@@ -246,12 +988,14 @@ fn decode_bw_image(
     };
     match bits_per_sample {
         1 => bilevel::decode_image(
+            bytes,
             fields,
             info,
             photo_interp == ifd::tags::PhotometricInterpretation::WhiteIsZero,
         )
         .map(DecodedImage::BilevelImage),
         4 => grayscale_4bit::decode_image(
+            bytes,
             fields,
             info,
             photo_interp == ifd::tags::PhotometricInterpretation::WhiteIsZero,
@@ -264,42 +1008,754 @@ fn decode_bw_image(
             photo_interp == ifd::tags::PhotometricInterpretation::WhiteIsZero,
         )
         .map(DecodedImage::Grayscale8BitImage),
-        // TODO: Use DecodeError::InvalidTagValues
-        _ => Err(DecodeError::TiffFieldError),
+        16 => grayscale_16bit::decode_image(
+            bytes,
+            fields,
+            info,
+            photo_interp == ifd::tags::PhotometricInterpretation::WhiteIsZero,
+        )
+        .map(DecodedImage::Grayscale16BitImage),
+        _ => generic::decode_image(
+            bytes,
+            fields,
+            info,
+            photo_interp == ifd::tags::PhotometricInterpretation::WhiteIsZero,
+        )
+        .map(DecodedImage::GenericImage),
+    }
+}
+
+fn decode_rgb_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+) -> Result<DecodedImage, DecodeError> {
+    let bits_per_sample = match tiff_field::find(&fields, ifd::Tag::BitsPerSample) {
+        Some(values) => tiff_field::read_shorts(values)?.to_vec(),
+        None => Vec::new(),
+    };
+    match (read_samples_per_pixel(&fields)?, &bits_per_sample[..]) {
+        (4, _) => rgba::decode_image(bytes, fields, info).map(DecodedImage::RGBAImage),
+        (3, [16, 16, 16]) => rgb16::decode_image(bytes, fields, info).map(DecodedImage::RGB16Image),
+        // Other depths, including different depths for each sample
+        (3, bits_per_sample) if bits_per_sample != [8, 8, 8] => {
+            generic::decode_image(bytes, fields, info, false).map(DecodedImage::GenericImage)
+        }
+        _ => rgb::decode_image(bytes, fields, info).map(DecodedImage::RGBImage),
     }
 }
 
+fn decode_float_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+    photo_interp: ifd::tags::PhotometricInterpretation,
+) -> Result<DecodedImage, DecodeError> {
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?
+        .to_vec();
+    match (photo_interp, &bits_per_sample[..]) {
+        (ifd::tags::PhotometricInterpretation::BlackIsZero, [16]) => {
+            sample::decode_grayscale_image(bytes, &info)
+                .and_then(|image| FloatImage::new(image, &fields))
+                .map(DecodedImage::GrayscaleF16Image)
+        }
+        (ifd::tags::PhotometricInterpretation::BlackIsZero, [32]) => {
+            sample::decode_grayscale_image(bytes, &info)
+                .and_then(|image| FloatImage::new(image, &fields))
+                .map(DecodedImage::GrayscaleF32Image)
+        }
+        (ifd::tags::PhotometricInterpretation::BlackIsZero, [64]) => {
+            sample::decode_grayscale_image(bytes, &info)
+                .and_then(|image| FloatImage::new(image, &fields))
+                .map(DecodedImage::GrayscaleF64Image)
+        }
+        (ifd::tags::PhotometricInterpretation::RGB, [16, 16, 16]) => {
+            sample::decode_rgb_image(bytes, &info)
+                .and_then(|image| FloatImage::new(image, &fields))
+                .map(DecodedImage::RGBF16Image)
+        }
+        (ifd::tags::PhotometricInterpretation::RGB, [32, 32, 32]) => {
+            sample::decode_rgb_image(bytes, &info)
+                .and_then(|image| FloatImage::new(image, &fields))
+                .map(DecodedImage::RGBF32Image)
+        }
+        (ifd::tags::PhotometricInterpretation::RGB, [64, 64, 64]) => {
+            sample::decode_rgb_image(bytes, &info)
+                .and_then(|image| FloatImage::new(image, &fields))
+                .map(DecodedImage::RGBF64Image)
+        }
+        (
+            ifd::tags::PhotometricInterpretation::BlackIsZero
+            | ifd::tags::PhotometricInterpretation::RGB,
+            _,
+        ) => Err(DecodeError::UnsupportedBitsPerSample(bits_per_sample)),
+        _ => Err(DecodeError::UnsupportedSampleFormat),
+    }
+}
+
+fn decode_signed_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+    photo_interp: ifd::tags::PhotometricInterpretation,
+) -> Result<DecodedImage, DecodeError> {
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?
+        .to_vec();
+    match (photo_interp, &bits_per_sample[..]) {
+        (ifd::tags::PhotometricInterpretation::BlackIsZero, [8]) => {
+            sample::decode_grayscale_image(bytes, &info).map(DecodedImage::GrayscaleI8Image)
+        }
+        (ifd::tags::PhotometricInterpretation::BlackIsZero, [16]) => {
+            sample::decode_grayscale_image(bytes, &info).map(DecodedImage::GrayscaleI16Image)
+        }
+        (ifd::tags::PhotometricInterpretation::BlackIsZero, [32]) => {
+            sample::decode_grayscale_image(bytes, &info).map(DecodedImage::GrayscaleI32Image)
+        }
+        (ifd::tags::PhotometricInterpretation::RGB, [8, 8, 8]) => {
+            sample::decode_rgb_image(bytes, &info).map(DecodedImage::RGBI8Image)
+        }
+        (ifd::tags::PhotometricInterpretation::RGB, [16, 16, 16]) => {
+            sample::decode_rgb_image(bytes, &info).map(DecodedImage::RGBI16Image)
+        }
+        (ifd::tags::PhotometricInterpretation::RGB, [32, 32, 32]) => {
+            sample::decode_rgb_image(bytes, &info).map(DecodedImage::RGBI32Image)
+        }
+        (
+            ifd::tags::PhotometricInterpretation::BlackIsZero
+            | ifd::tags::PhotometricInterpretation::RGB,
+            _,
+        ) => Err(DecodeError::UnsupportedBitsPerSample(bits_per_sample)),
+        _ => Err(DecodeError::UnsupportedSampleFormat),
+    }
+}
+
+/// Takes the metadata fields out of the fields. Fields that can't be read are
+/// left unset, with their errors added to `field_errors`.
+fn read_metadata(fields: &mut Vec<ifd::Entry>, field_errors: &mut Vec<DecodeError>) -> Metadata {
+    let mut metadata = Metadata::new();
+    for tag in Metadata::TAGS {
+        if let Some(values) = tiff_field::take(fields, tag) {
+            match tiff_field::read_ascii(&values) {
+                Ok(value) => *metadata.field_mut(tag) = Some(value.to_owned()),
+                Err(err) => field_errors.push(err),
+            }
+        }
+    }
+    metadata
+}
+
+fn read_samples_per_pixel(fields: &[ifd::Entry]) -> Result<u16, DecodeError> {
+    match tiff_field::find(fields, ifd::Tag::SamplesPerPixel) {
+        Some(values) => tiff_field::read_single_short(values),
+        None => Ok(1),
+    }
+}
+
+/// Reads whether the single extra sample of an image is associated or
+/// unassociated alpha.
+fn read_alpha(fields: &[ifd::Entry]) -> Result<colors::Alpha, DecodeError> {
+    let extra_samples = tiff_field::find_required(fields, ifd::Tag::ExtraSamples)
+        .and_then(tiff_field::read_single_short)
+        .and_then(|extra_samples| {
+            // TODO: Use DecodeError::InvalidTagValues
+            ifd::tags::ExtraSamples::from_repr(extra_samples).ok_or(DecodeError::TiffFieldError)
+        })?;
+    match extra_samples {
+        ifd::tags::ExtraSamples::AssociatedAlpha => Ok(colors::Alpha::Associated),
+        ifd::tags::ExtraSamples::UnassociatedAlpha => Ok(colors::Alpha::Unassociated),
+        ifd::tags::ExtraSamples::Unspecified => Err(DecodeError::UnsupportedExtraSamples),
+    }
+}
+
+/// The entries read from an IFD.
+struct ImageFieldDirectory {
+    fields: Vec<ifd::Entry>,
+    /// Entries with unknown tags
+    raw_entries: Vec<ifd::RawEntry>,
+    /// Errors reading entries that were skipped
+    field_errors: Vec<DecodeError>,
+}
+
+enum FieldEntry {
+    Known(ifd::Entry),
+    /// An entry with an unknown tag
+    Raw(ifd::RawEntry),
+    /// An entry with an unknown tag that points to other data in the file
+    Dropped,
+}
+
+/// Unknown tags whose values are offsets to other data in the file: FreeOffsets,
+/// SubIFDs, JPEGInterchangeFormat, ExifIFD, GPSInfo and Interoperability.
+const OFFSET_TAGS: [u16; 6] = [288, 330, 513, 34665, 34853, 40965];
+
+/// Reads the entries of an IFD, keeping entries with unknown tags as raw
+/// entries. Entries that point to other data in the file, which are those with
+/// a tag in [`OFFSET_TAGS`] or of type IFD8, are dropped instead.
 fn read_image_field_directory(
     bytes: &[u8],
     endianness: Endianness,
     ifd: Ifd<'_>,
-) -> Result<(Vec<ifd::Entry>, Vec<DecodeError>), DecodeError> {
+) -> Result<ImageFieldDirectory, DecodeError> {
     let entries = ifd.fields().map(|field| -> Result<_, DecodeError> {
         let mut field = io::Cursor::new(field);
         let raw_tag = endianness.read_u16(field.borrow_mut()).unwrap();
-        let tag: ifd::Tag =
-            ifd::Tag::from_repr(raw_tag).ok_or(DecodeError::UnknownFieldTag(raw_tag))?;
+        let tag = ifd::Tag::from_repr(raw_tag);
         let raw_field_type = endianness.read_u16(field.borrow_mut()).unwrap();
         let field_type = ifd::Type::from_repr(raw_field_type)
             .ok_or(DecodeError::UnknownFieldType(raw_field_type))?;
-        let val_count = endianness.read_u32(field.borrow_mut()).unwrap() as usize;
+        let val_count = match ifd.format {
+            ifd::Format::Classic => endianness.read_u32(field.borrow_mut()).unwrap() as u64,
+            ifd::Format::BigTiff => endianness.read_u64(field.borrow_mut()).unwrap(),
+        }
+        .try_into()
+        .map_err(|_| DecodeError::CantReadField)?;
         let val_offset_buff = field.fill_buf().unwrap();
-        debug_assert_eq!(val_offset_buff.len(), 4);
-        let values =
-            tiff_field::read_values(bytes, endianness, field_type, val_count, val_offset_buff)?;
+        debug_assert_eq!(val_offset_buff.len(), ifd.format.offset_len());
+        let values = tiff_field::read_values(
+            bytes,
+            endianness,
+            ifd.format,
+            match (tag, field_type) {
+                // Keep the exact bytes of unknown ASCII entries
+                (None, ifd::Type::ASCII) => ifd::Type::Byte,
+                _ => field_type,
+            },
+            val_count,
+            val_offset_buff,
+        )?;
 
-        Ok(ifd::Entry::new(tag, values))
+        Ok(match tag {
+            Some(tag) => FieldEntry::Known(ifd::Entry::new(tag, values)),
+            // Offsets would point at unrelated data once written to another
+            // file
+            None if OFFSET_TAGS.contains(&raw_tag) || field_type == ifd::Type::Ifd8 => {
+                FieldEntry::Dropped
+            }
+            None => FieldEntry::Raw(ifd::RawEntry::new(raw_tag, field_type, values)),
+        })
     });
 
     let mut field_errors = Vec::new();
-    let entries: Vec<_> = entries
+    let mut raw_entries = Vec::new();
+    let fields = entries
         .filter_map(|entry| match entry {
-            Ok(entry) => Some(entry),
+            Ok(FieldEntry::Known(entry)) => Some(entry),
+            Ok(FieldEntry::Raw(raw_entry)) => {
+                raw_entries.push(raw_entry);
+                None
+            }
+            Ok(FieldEntry::Dropped) => None,
             Err(err) => {
                 field_errors.push(err);
                 None
             }
         })
         .collect();
-    Ok((entries, field_errors))
+    Ok(ImageFieldDirectory {
+        fields,
+        raw_entries,
+        field_errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::LittleEndian;
+
+    use super::*;
+    use crate::{
+        encode::{
+            compression::NoCompression, grayscale_8bit::*, layout::TileSize, ImageEncoder, Layout,
+        },
+        test_util::{assert_same_pixels, decode_single, decoded_as, handmade_tiff},
+    };
+
+    /// Builds a 20x18 uncompressed 8-bit grayscale image with 16x16 tiles,
+    /// whose padding past the right and bottom edges is filled with 0xEE.
+    fn tiled_tiff(tile_byte_count: u32) -> Vec<u8> {
+        let pixel = |x: usize, y: usize| (x * 3 + y * 11) as u8;
+        let mut data = Vec::new();
+        for tile in 0..4 {
+            let (tile_x, tile_y) = ((tile % 2) * 16, (tile / 2) * 16);
+            for y in tile_y..tile_y + 16 {
+                for x in tile_x..tile_x + 16 {
+                    data.push(if x < 20 && y < 18 { pixel(x, y) } else { 0xEE });
+                }
+            }
+        }
+        let offsets_offset = 8 + data.len() as u32;
+        data.extend(
+            [0u32, 256, 512, 768]
+                .map(|offset| offset + 8)
+                .map(u32::to_le_bytes)
+                .concat(),
+        );
+        data.extend([tile_byte_count; 4].map(u32::to_le_bytes).concat());
+        // 72 dpi
+        data.extend([72u32, 1].map(u32::to_le_bytes).concat());
+        handmade_tiff(
+            &data,
+            &[
+                (256, 4, 1, 20),
+                (257, 4, 1, 18),
+                (258, 3, 1, 8),
+                (259, 3, 1, 1),
+                (262, 3, 1, 1),
+                (282, 5, 1, offsets_offset + 32),
+                (283, 5, 1, offsets_offset + 32),
+                (322, 3, 1, 16),
+                (323, 3, 1, 16),
+                (324, 4, 4, offsets_offset),
+                (325, 4, 4, offsets_offset + 16),
+            ],
+        )
+    }
+
+    #[test]
+    fn padded_tiles_are_cropped() {
+        let image: Image<colors::Grayscale8Bit> = decoded_as(decode_single(&tiled_tiff(256)));
+        assert_eq!((image.width(), image.height()), (20, 18));
+        for (y, row) in image.iter_pixels().enumerate() {
+            for (x, pixel) in row.enumerate() {
+                assert_eq!(pixel.0, (x * 3 + y * 11) as u8, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn short_tiles_are_reported() {
+        let decoded = decode_images(&tiled_tiff(200)).unwrap();
+        assert!(decoded.pages.is_empty());
+        assert!(matches!(
+            decoded.errors[..],
+            [DecodeError::ShortTile {
+                tile: 0,
+                expected_len: 256,
+                len: 200
+            }]
+        ));
+    }
+
+    #[test]
+    fn alpha_is_kept_and_converted() {
+        use crate::encode::{
+            compression::NoCompression, grayscale_alpha::*, rgba::RGBAImageEncoder, ImageEncoder,
+        };
+        use byteorder::{BigEndian, LittleEndian};
+
+        let rgba = |r, g, b| Image::new(vec![colors::RGBA::new(r, g, b, 128)], 1, 1);
+        let decode_rgba = |encoded: &[u8]| match decode_single(encoded) {
+            DecodedImage::RGBAImage(image) => image,
+            _ => panic!("not an RGBA image"),
+        };
+        let unassociated = rgba(200, 100, 50);
+        let associated = rgba(100, 50, 25);
+        // Unassociating loses some precision
+        let unassociated_again = rgba(199, 100, 50);
+        for (image, alpha, as_unassociated) in [
+            (&unassociated, colors::Alpha::Unassociated, &unassociated),
+            (&associated, colors::Alpha::Associated, &unassociated_again),
+        ] {
+            for encoded in [
+                RGBAImageEncoder::<LittleEndian, _>::new(image, NoCompression, alpha).encode(),
+                RGBAImageEncoder::<BigEndian, _>::new(image, NoCompression, alpha).encode(),
+            ] {
+                let decoded = decode_rgba(&encoded);
+                assert_eq!(decoded.alpha(), alpha);
+                assert_same_pixels(decoded.image(), image);
+                assert_same_pixels(&decoded.into_image(colors::Alpha::Associated), &associated);
+                assert_same_pixels(
+                    &decode_rgba(&encoded).into_image(colors::Alpha::Unassociated),
+                    as_unassociated,
+                );
+            }
+        }
+
+        let image = Image::new(vec![colors::GrayscaleAlpha::new(200, 128)], 1, 1);
+        let encoded = GrayscaleAlphaImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            colors::Alpha::Unassociated,
+            WhiteIsZero,
+        )
+        .encode();
+        let DecodedImage::GrayscaleAlphaImage(decoded) = decode_single(&encoded) else {
+            panic!("not a grayscale with alpha image")
+        };
+        assert_eq!(decoded.alpha(), colors::Alpha::Unassociated);
+        assert_same_pixels(decoded.image(), &image);
+        assert!(decoded
+            .into_image(colors::Alpha::Associated)
+            .iter_pixels()
+            .flatten()
+            .eq([colors::GrayscaleAlpha::new(100, 128)]));
+    }
+
+    /// Builds a 1x1 grayscale image with a single 32-bit sample of 1.5, the
+    /// given extra entries and a 2.5 DOUBLE at offset 12.
+    fn float_tiff(bits_per_sample: u32, extra_entries: &[(u16, u16, u32, u32)]) -> Vec<u8> {
+        let mut data = 1.5f32.to_le_bytes().to_vec();
+        data.extend(2.5f64.to_le_bytes());
+        // 72 dpi
+        data.extend([72u32, 1].map(u32::to_le_bytes).concat());
+        let mut entries = vec![
+            (256, 3, 1, 1),
+            (257, 3, 1, 1),
+            (258, 3, 1, bits_per_sample),
+            (259, 3, 1, 1),
+            (262, 3, 1, 1),
+            (273, 4, 1, 8),
+            (279, 4, 1, 4),
+            (282, 5, 1, 20),
+            (283, 5, 1, 20),
+        ];
+        entries.extend(extra_entries);
+        entries.sort_by_key(|entry| entry.0);
+        handmade_tiff(&data, &entries)
+    }
+
+    #[test]
+    fn sample_format_and_range() {
+        let DecodedImage::GrayscaleF32Image(image) =
+            decode_single(&float_tiff(32, &[(339, 3, 1, 3)]))
+        else {
+            panic!("not a 32-bit float image")
+        };
+        assert!(image
+            .image()
+            .iter_pixels()
+            .flatten()
+            .eq([colors::SampleGrayscale(1.5)]));
+        assert_eq!(image.min_sample_value(), None);
+        assert_eq!(image.max_sample_value(), None);
+
+        // SMinSampleValue as an inline FLOAT and SMaxSampleValue as a DOUBLE
+        let encoded = float_tiff(
+            32,
+            &[
+                (339, 3, 1, 3),
+                (340, 11, 1, (-0.5f32).to_bits()),
+                (341, 12, 1, 12),
+            ],
+        );
+        let DecodedImage::GrayscaleF32Image(image) = decode_single(&encoded) else {
+            panic!("not a 32-bit float image")
+        };
+        assert_eq!(image.min_sample_value(), Some(&[-0.5][..]));
+        assert_eq!(image.max_sample_value(), Some(&[2.5][..]));
+
+        // Without a SampleFormat the samples are unsigned integers
+        let DecodedImage::GenericImage(image) = decode_single(&float_tiff(32, &[])) else {
+            panic!("not a generic image")
+        };
+        assert_eq!(image.bits_per_sample(), [32]);
+        assert_eq!(image.samples(), [1.5f32.to_bits()]);
+        assert!(matches!(
+            decode_images(&float_tiff(24, &[(339, 3, 1, 3)]))
+                .unwrap()
+                .errors[..],
+            [DecodeError::UnsupportedBitsPerSample(_)]
+        ));
+        // The same samples as signed integers
+        let DecodedImage::GrayscaleI32Image(image) =
+            decode_single(&float_tiff(32, &[(339, 3, 1, 2)]))
+        else {
+            panic!("not a 32-bit signed image")
+        };
+        assert!(image
+            .iter_pixels()
+            .flatten()
+            .eq([colors::SampleGrayscale(1.5f32.to_bits() as i32)]));
+        assert!(matches!(
+            decode_images(&float_tiff(24, &[(339, 3, 1, 2)]))
+                .unwrap()
+                .errors[..],
+            [DecodeError::UnsupportedBitsPerSample(_)]
+        ));
+        // Samples that differ in format
+        let encoded = float_tiff(32, &[(277, 3, 2, 1), (339, 3, 2, 3 | 1 << 16)]);
+        assert!(matches!(
+            decode_images(&encoded).unwrap().errors[..],
+            [DecodeError::UnsupportedSampleFormat]
+        ));
+    }
+
+    /// The entries of a 2x2 uncompressed 8-bit grayscale image whose data is
+    /// at offset 8, with the resolution read from the same offset.
+    const GRAYSCALE_ENTRIES: [(u16, u16, u32, u32); 8] = [
+        (256, 4, 1, 2),
+        (257, 4, 1, 2),
+        (258, 3, 1, 8),
+        (262, 3, 1, 1),
+        (273, 4, 1, 8),
+        (279, 4, 1, 4),
+        (282, 5, 1, 8),
+        (283, 5, 1, 8),
+    ];
+
+    /// The grayscale entries with the given entries added or replaced.
+    fn with_entries(extra: &[(u16, u16, u32, u32)]) -> Vec<(u16, u16, u32, u32)> {
+        let mut entries = GRAYSCALE_ENTRIES.to_vec();
+        entries.retain(|entry| extra.iter().all(|extra| extra.0 != entry.0));
+        entries.extend(extra);
+        entries.sort_by_key(|entry| entry.0);
+        entries
+    }
+
+    #[test]
+    fn unreadable_metadata_field_is_skipped() {
+        // Make stored as BYTE instead of ASCII
+        let bytes = handmade_tiff(
+            &[1, 2, 3, 4],
+            &with_entries(&[
+                (271, 1, 4, u32::from_le_bytes(*b"Acme")),
+                (305, 2, 4, u32::from_le_bytes(*b"abc\0")),
+            ]),
+        );
+        let decoded = decode_images(&bytes).unwrap();
+        assert!(matches!(decoded.errors[..], [DecodeError::TiffFieldError]));
+        assert_eq!(decoded.pages[0].metadata.make, None);
+        assert_eq!(decoded.pages[0].metadata.software.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn ascii_padding_is_trimmed() {
+        let mut data = vec![1, 2, 3, 4, 0, 0, 0, 0];
+        data.extend(b"Acme\0\0");
+        let bytes = handmade_tiff(&data, &with_entries(&[(271, 2, 6, 16)]));
+        let decoded = decode_images(&bytes).unwrap();
+        assert!(decoded.errors.is_empty());
+        assert_eq!(decoded.pages[0].metadata.make.as_deref(), Some("Acme"));
+    }
+
+    #[test]
+    fn padded_ink_names_are_trimmed() {
+        // A 1x1 CMYK pixel, then BitsPerSample, the resolution and InkNames
+        let mut data = vec![1, 2, 3, 4];
+        data.extend([8u16; 4].map(u16::to_le_bytes).concat());
+        data.extend([72u32, 1].map(u32::to_le_bytes).concat());
+        let ink_names = b"Cyan\0Magenta\0Yellow\0Black\0\0\0";
+        data.extend(ink_names);
+        let bytes = handmade_tiff(
+            &data,
+            &[
+                (256, 4, 1, 1),
+                (257, 4, 1, 1),
+                (258, 3, 4, 12),
+                (262, 3, 1, 5),
+                (273, 4, 1, 8),
+                (277, 3, 1, 4),
+                (279, 4, 1, 4),
+                (282, 5, 1, 20),
+                (283, 5, 1, 20),
+                (333, 2, ink_names.len() as u32, 28),
+            ],
+        );
+        let decoded = decode_images(&bytes).unwrap();
+        assert!(decoded.errors.is_empty(), "{:?}", decoded.errors);
+        let DecodedImage::CMYKImage(image) = &decoded.pages[0].image else {
+            panic!("not a CMYK image")
+        };
+        assert_eq!(
+            image.ink_names(),
+            Some(&["Cyan", "Magenta", "Yellow", "Black"].map(String::from)[..])
+        );
+    }
+
+    #[test]
+    fn overlong_compressed_chunks_are_reported() {
+        // 5 bytes of PackBits data for a 4 byte strip or tile
+        let data = [4, 1, 2, 3, 4, 5];
+        let strips = handmade_tiff(&data, &with_entries(&[(259, 3, 1, 32773), (279, 4, 1, 6)]));
+        let decoded = decode_images(&strips).unwrap();
+        assert!(matches!(
+            decoded.errors[..],
+            [DecodeError::OverlongStrip {
+                strip: 0,
+                expected_len: 4,
+                len: 5
+            }]
+        ));
+
+        let mut entries = with_entries(&[
+            (259, 3, 1, 32773),
+            (322, 3, 1, 2),
+            (323, 3, 1, 2),
+            (324, 4, 1, 8),
+            (325, 4, 1, 6),
+        ]);
+        entries.retain(|&(tag, ..)| tag != 273 && tag != 279);
+        let decoded = decode_images(&handmade_tiff(&data, &entries)).unwrap();
+        assert!(matches!(
+            decoded.errors[..],
+            [DecodeError::OverlongTile {
+                tile: 0,
+                expected_len: 4,
+                len: 5
+            }]
+        ));
+    }
+
+    #[test]
+    fn planes_must_have_the_same_number_of_strips() {
+        // A 1x1 planar RGB image with a strip for each plane
+        let mut data = vec![10, 20, 30, 0];
+        data.extend([8u32, 9, 10, 11].into_iter().flat_map(u32::to_le_bytes));
+        data.extend([1u32; 4].into_iter().flat_map(u32::to_le_bytes));
+        data.extend([8u16; 3].into_iter().flat_map(u16::to_le_bytes));
+        let entries = |strip_count| {
+            [
+                (256, 4, 1, 1),
+                (257, 4, 1, 1),
+                (258, 3, 3, 44),
+                (262, 3, 1, 2),
+                (273, 4, strip_count, 12),
+                (277, 3, 1, 3),
+                (278, 4, 1, 1),
+                (279, 4, strip_count, 28),
+                (282, 5, 1, 8),
+                (283, 5, 1, 8),
+                (284, 3, 1, 2),
+            ]
+        };
+
+        let decoded = decode_images(&handmade_tiff(&data, &entries(3))).unwrap();
+        let DecodedImage::RGBImage(image) = &decoded.pages[0].image else {
+            panic!("not an RGB image")
+        };
+        assert!(image
+            .iter_pixels()
+            .flatten()
+            .eq([colors::RGB::new(10, 20, 30)]));
+
+        let decoded = decode_images(&handmade_tiff(&data, &entries(4))).unwrap();
+        assert!(decoded.pages.is_empty());
+        assert!(matches!(decoded.errors[..], [DecodeError::TiffFieldError]));
+    }
+
+    /// Builds a one row planar RGB image with the given rows for each plane.
+    fn planar_rgb_tiff(width: u32, bits: u16, planes: [&[u8]; 3]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        for plane in planes {
+            offsets.push(8 + data.len() as u32);
+            data.extend(plane);
+        }
+        data.resize(data.len().next_multiple_of(2), 0);
+        let offsets_offset = 8 + data.len() as u32;
+        data.extend(offsets.into_iter().flat_map(u32::to_le_bytes));
+        data.extend(
+            planes
+                .map(|plane| plane.len() as u32)
+                .into_iter()
+                .flat_map(u32::to_le_bytes),
+        );
+        data.extend([bits; 3].into_iter().flat_map(u16::to_le_bytes));
+        handmade_tiff(
+            &data,
+            &[
+                (256, 4, 1, width),
+                (257, 4, 1, 1),
+                (258, 3, 3, offsets_offset + 24),
+                (262, 3, 1, 2),
+                (273, 4, 3, offsets_offset),
+                (277, 3, 1, 3),
+                (278, 4, 1, 1),
+                (279, 4, 3, offsets_offset + 12),
+                (282, 5, 1, 8),
+                (283, 5, 1, 8),
+                (284, 3, 1, 2),
+            ],
+        )
+    }
+
+    #[test]
+    fn planar_samples_that_are_not_whole_bytes() {
+        let decoded = decode_images(&planar_rgb_tiff(
+            2,
+            12,
+            [
+                &[0xAB, 0xC1, 0x23],
+                &[0x45, 0x67, 0x89],
+                &[0xDE, 0xF0, 0x01],
+            ],
+        ))
+        .unwrap();
+        let DecodedImage::GenericImage(image) = &decoded.pages[0].image else {
+            panic!("not a generic image")
+        };
+        assert_eq!(image.samples(), [0xABC, 0x456, 0xDEF, 0x123, 0x789, 0x001]);
+
+        let decoded = decode_images(&planar_rgb_tiff(
+            3,
+            2,
+            [&[0b01_10_11_00], &[0b11_00_01_00], &[0b10_10_10_00]],
+        ))
+        .unwrap();
+        let DecodedImage::GenericImage(image) = &decoded.pages[0].image else {
+            panic!("not a generic image")
+        };
+        assert_eq!(image.samples(), [1, 3, 2, 2, 0, 2, 3, 1, 2]);
+    }
+
+    #[test]
+    fn rgb_with_mixed_bit_depths_is_generic() {
+        // One 5-6-5 pixel
+        let bytes = handmade_tiff(
+            &[0b1010_1110, 0b0111_1011, 0, 0, 0, 0, 0, 0, 5, 0, 6, 0, 5, 0],
+            &with_entries(&[
+                (258, 3, 3, 16),
+                (256, 4, 1, 1),
+                (257, 4, 1, 1),
+                (262, 3, 1, 2),
+                (277, 3, 1, 3),
+                (279, 4, 1, 2),
+            ]),
+        );
+        let decoded = decode_images(&bytes).unwrap();
+        let DecodedImage::GenericImage(image) = &decoded.pages[0].image else {
+            panic!("not a generic image")
+        };
+        assert_eq!(image.bits_per_sample(), [5, 6, 5]);
+        assert_eq!(image.samples(), [0b10101, 0b110011, 0b11011]);
+    }
+
+    /// Replaces the value of the entry with `tag` in the first IFD of a little
+    /// endian BigTIFF with a single LONG8.
+    fn set_bigtiff_long8(encoded: &mut [u8], tag: u16, value: u64) {
+        let read_u64 = |encoded: &[u8], inx: usize| {
+            u64::from_le_bytes(encoded[inx..inx + 8].try_into().unwrap())
+        };
+        let ifd_inx = read_u64(encoded, 8) as usize;
+        let inx = (0..read_u64(encoded, ifd_inx) as usize)
+            .map(|entry| ifd_inx + 8 + entry * 20)
+            .find(|&inx| u16::from_le_bytes([encoded[inx], encoded[inx + 1]]) == tag)
+            .expect("tag not found");
+        encoded[inx + 2..inx + 4].copy_from_slice(&16u16.to_le_bytes());
+        encoded[inx + 4..inx + 12].copy_from_slice(&1u64.to_le_bytes());
+        encoded[inx + 12..inx + 20].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn offsets_near_the_end_of_the_address_space_are_an_error() {
+        let image = Image::new(vec![colors::Grayscale8Bit(1); 4], 2, 2);
+        for (layout, offsets_tag) in [
+            (Layout::SingleStrip, 273),
+            (Layout::Tiles(TileSize::new(16, 16)), 324),
+        ] {
+            let mut encoded = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+                &image,
+                NoCompression,
+                BlackIsZero,
+            )
+            .with_layout(layout)
+            .encode_bigtiff();
+            set_bigtiff_long8(&mut encoded, offsets_tag, u64::MAX - 1);
+            let decoded = decode_images(&encoded).unwrap();
+            assert!(decoded.pages.is_empty());
+            assert!(matches!(decoded.errors[..], [DecodeError::CantReadImage]));
+        }
+    }
 }
@@ -1,9 +1,129 @@
-use super::{DecodeError, ImageInfo};
-use crate::{colors, ifd, Image};
+use super::{tiff_field, DecodeError, ImageInfo, PaletteImage};
+use crate::{colors, ifd};
 
 pub(crate) fn decode_image(
+    bytes: &[u8],
     fields: Vec<ifd::Entry>,
     info: ImageInfo,
-) -> Result<Image<colors::RGB>, DecodeError> {
-    todo!()
+) -> Result<PaletteImage, DecodeError> {
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_single_short)?;
+    let color_count = match bits_per_sample {
+        4 | 8 => 1 << bits_per_sample,
+        _ => return Err(DecodeError::UnsupportedBitsPerSample(vec![bits_per_sample])),
+    };
+
+    // All red values come first, then all green values, then all blue values
+    let color_map =
+        tiff_field::find_required(&fields, ifd::Tag::ColorMap).and_then(tiff_field::read_shorts)?;
+    if color_map.len() != color_count * 3 {
+        return Err(DecodeError::TiffFieldError);
+    }
+    let (reds, rest) = color_map.split_at(color_count);
+    let (greens, blues) = rest.split_at(color_count);
+    let color_map = colors::ColorMap::from_colors(
+        reds.iter()
+            .zip(greens)
+            .zip(blues)
+            .map(|((r, g), b)| colors::RGB::new((r >> 8) as u8, (g >> 8) as u8, (b >> 8) as u8))
+            .collect(),
+    );
+
+    let indices = if bits_per_sample == 8 {
+        info.read_rows(bytes, &[8])?
+    } else {
+        // 4 bits per sample, with each row starting on a new byte
+        info.read_rows(bytes, &[4])?
+            .chunks_exact(info.width.div_ceil(2))
+            .flat_map(|row| {
+                row.iter()
+                    .flat_map(|byte| [byte >> 4, byte & 0b1111])
+                    .take(info.width)
+            })
+            .collect()
+    };
+    if indices.len() != info.width * info.height || indices.is_empty() {
+        return Err(DecodeError::CantReadImage);
+    }
+
+    Ok(PaletteImage {
+        color_map,
+        indices,
+        width: info.width,
+        height: info.height,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use crate::{
+        colors::{ColorMap, RGB},
+        decode::{DecodedImage, PaletteImage},
+        encode::{
+            compression::{NoCompression, PackBits},
+            palette_color::PaletteColorImageEncoder,
+            ImageEncoder,
+        },
+        test_util::decode_single,
+    };
+
+    fn decode_palette_image(encoded: &[u8]) -> PaletteImage {
+        let DecodedImage::PaletteImage(image) = decode_single(encoded) else {
+            panic!("not a palette-color image")
+        };
+        image
+    }
+
+    /// A color map with `count` colors, and indices into it for a 5x3 image.
+    fn palette(count: u8) -> (ColorMap, Vec<u8>) {
+        let mut color_map = ColorMap::new();
+        for i in 0..count {
+            color_map.try_add_color(RGB::new(i, 255 - i, i / 2));
+        }
+        let indices = (0..15).map(|i| (i * 7) % count).collect();
+        (color_map, indices)
+    }
+
+    #[test]
+    fn palette_round_trip() {
+        // 5 colors take 4 bits per index, and 20 take 8
+        for count in [5, 20] {
+            let (color_map, indices) = palette(count);
+            let image = color_map.try_new_indexed_image(&indices, 5, 3).unwrap();
+            for encoded in [
+                PaletteColorImageEncoder::<LittleEndian, _>::new(&image, NoCompression).encode(),
+                PaletteColorImageEncoder::<BigEndian, _>::new(&image, PackBits).encode(),
+            ] {
+                let decoded = decode_palette_image(&encoded);
+                assert_eq!((decoded.width(), decoded.height()), (5, 3));
+                assert_eq!(decoded.indices(), indices);
+                assert!(decoded.color_map().colors().starts_with(color_map.colors()));
+            }
+        }
+    }
+
+    #[test]
+    fn decoded_palette_image_can_be_re_encoded() {
+        for count in [5, 20] {
+            let (color_map, indices) = palette(count);
+            let image = color_map.try_new_indexed_image(&indices, 5, 3).unwrap();
+            let decoded = decode_palette_image(
+                &PaletteColorImageEncoder::<LittleEndian, _>::new(&image, NoCompression).encode(),
+            );
+
+            let decoded_image = decoded.image();
+            let re_encoded =
+                PaletteColorImageEncoder::<LittleEndian, _>::new(&decoded_image, NoCompression)
+                    .encode();
+            let re_decoded = decode_palette_image(&re_encoded);
+            assert_eq!(re_decoded.indices(), indices);
+            assert!(re_decoded.color_map().colors() == decoded.color_map().colors());
+            assert!(decoded_image
+                .iter_pixels()
+                .flatten()
+                .eq(image.iter_pixels().flatten()));
+        }
+    }
 }
@@ -1,9 +1,88 @@
-use super::{DecodeError, ImageInfo};
+use super::{read_samples_per_pixel, tiff_field, DecodeError, ImageInfo};
 use crate::{colors, ifd, Image};
 
 pub(crate) fn decode_image(
+    bytes: &[u8],
     fields: Vec<ifd::Entry>,
     info: ImageInfo,
 ) -> Result<Image<colors::RGB>, DecodeError> {
-    todo!()
+    let samples_per_pixel = read_samples_per_pixel(&fields)?;
+    if samples_per_pixel != 3 {
+        return Err(DecodeError::UnsupportedSamplesPerPixel(samples_per_pixel));
+    }
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?;
+    if bits_per_sample != [8, 8, 8] {
+        return Err(DecodeError::UnsupportedBitsPerSample(
+            bits_per_sample.to_vec(),
+        ));
+    }
+
+    let pixels = info
+        .read_rows(bytes, bits_per_sample)?
+        .chunks_exact(3)
+        .map(|sample| colors::RGB::new(sample[0], sample[1], sample[2]))
+        .collect();
+
+    Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use crate::{
+        colors,
+        encode::{
+            compression::{Lzw, NoCompression, PackBits},
+            layout::TileSize,
+            rgb::RGBImageEncoder,
+            ImageEncoder, Layout, PlanarConfiguration,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    fn rgb_image(width: usize, height: usize) -> Image<colors::RGB> {
+        let pixels = (0..width * height)
+            .map(|i| colors::RGB::new((i * 16) as u8, (i * 5 + 100) as u8, (i * 7) as u8))
+            .collect();
+        Image::new(pixels, width, height)
+    }
+
+    #[test]
+    fn chunky_rgb_round_trip() {
+        let image = rgb_image(5, 3);
+        for encoded in [
+            RGBImageEncoder::<LittleEndian, _>::new(&image, NoCompression).encode(),
+            RGBImageEncoder::<BigEndian, _>::new(&image, NoCompression).encode(),
+            RGBImageEncoder::<LittleEndian, _>::new(&image, PackBits).encode(),
+            RGBImageEncoder::<BigEndian, _>::new(&image, Lzw).encode(),
+        ] {
+            assert_decodes_to(&encoded, &image);
+        }
+    }
+
+    #[test]
+    fn planar_rgb_round_trip() {
+        let image = rgb_image(40, 20);
+        for layout in [
+            Layout::SingleStrip,
+            Layout::RowsPerStrip(7),
+            Layout::Tiles(TileSize::new(32, 16)),
+        ] {
+            for encoded in [
+                RGBImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                    .with_layout(layout)
+                    .with_planar_configuration(PlanarConfiguration::Planar)
+                    .encode(),
+                RGBImageEncoder::<BigEndian, _>::new(&image, Lzw)
+                    .with_layout(layout)
+                    .with_planar_configuration(PlanarConfiguration::Planar)
+                    .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
 }
@@ -0,0 +1,34 @@
+use super::{read_samples_per_pixel, tiff_field, DecodeError, ImageInfo};
+use crate::{colors, ifd, Image};
+
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+) -> Result<Image<colors::RGB16>, DecodeError> {
+    let samples_per_pixel = read_samples_per_pixel(&fields)?;
+    if samples_per_pixel != 3 {
+        return Err(DecodeError::UnsupportedSamplesPerPixel(samples_per_pixel));
+    }
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?;
+    if bits_per_sample != [16, 16, 16] {
+        return Err(DecodeError::UnsupportedBitsPerSample(
+            bits_per_sample.to_vec(),
+        ));
+    }
+
+    let pixels = info
+        .read_rows(bytes, bits_per_sample)?
+        .chunks_exact(6)
+        .map(|samples| {
+            colors::RGB16::new(
+                info.read_u16(&samples[0..2]),
+                info.read_u16(&samples[2..4]),
+                info.read_u16(&samples[4..6]),
+            )
+        })
+        .collect();
+
+    Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
+}
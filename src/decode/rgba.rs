@@ -0,0 +1,27 @@
+use super::{read_alpha, tiff_field, AlphaImage, DecodeError, ImageInfo};
+use crate::{colors, ifd, Image};
+
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+) -> Result<AlphaImage<colors::RGBA>, DecodeError> {
+    let alpha = read_alpha(&fields)?;
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?;
+    if bits_per_sample != [8, 8, 8, 8] {
+        return Err(DecodeError::UnsupportedBitsPerSample(
+            bits_per_sample.to_vec(),
+        ));
+    }
+
+    let pixels = info
+        .read_rows(bytes, bits_per_sample)?
+        .chunks_exact(4)
+        .map(|sample| colors::RGBA::new(sample[0], sample[1], sample[2], sample[3]))
+        .collect();
+
+    let image =
+        Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)?;
+    Ok(AlphaImage { image, alpha })
+}
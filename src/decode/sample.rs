@@ -0,0 +1,42 @@
+use super::{DecodeError, ImageInfo};
+use crate::{
+    colors::{self, Sample},
+    Image,
+};
+
+pub(crate) fn decode_grayscale_image<T: Sample>(
+    bytes: &[u8],
+    info: &ImageInfo,
+) -> Result<Image<colors::SampleGrayscale<T>>, DecodeError> {
+    let sample_len = T::BITS as usize / 8;
+    let pixels = info
+        .read_rows(bytes, &[T::BITS])?
+        .chunks_exact(sample_len)
+        .map(|sample| colors::SampleGrayscale(T::read(info.endianness, sample)))
+        .collect();
+
+    Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
+}
+
+pub(crate) fn decode_rgb_image<T: Sample>(
+    bytes: &[u8],
+    info: &ImageInfo,
+) -> Result<Image<colors::SampleRGB<T>>, DecodeError> {
+    let sample_len = T::BITS as usize / 8;
+    let pixels = info
+        .read_rows(bytes, &[T::BITS; 3])?
+        .chunks_exact(sample_len * 3)
+        .map(|samples| {
+            let mut samples = samples
+                .chunks_exact(sample_len)
+                .map(|sample| T::read(info.endianness, sample));
+            colors::SampleRGB::new(
+                samples.next().unwrap(),
+                samples.next().unwrap(),
+                samples.next().unwrap(),
+            )
+        })
+        .collect();
+
+    Image::try_new(pixels, info.width, info.height).map_err(|_| DecodeError::CantReadImage)
+}
@@ -1,10 +1,8 @@
-use std::ffi::CStr;
-
 use byteordered::{Endian, Endianness};
 
 use crate::{
     ifd,
-    types::{Long, Short, URational},
+    types::{Long, SRational, Short, URational},
 };
 
 use super::DecodeError;
@@ -12,88 +10,128 @@ use super::DecodeError;
 pub(crate) fn read_values(
     bytes: &[u8],
     endianness: Endianness,
+    format: ifd::Format,
     valtype: ifd::Type,
     count: usize,
     offset_buff: &[u8],
 ) -> Result<ifd::Values, DecodeError> {
+    // Values are stored in the offset field itself if they fit
+    let len = count
+        .checked_mul(valtype.size())
+        .ok_or(DecodeError::CantReadField)?;
+    let val_buff = if len <= format.offset_len() {
+        &offset_buff[..len]
+    } else {
+        let index = read_offset(endianness, format, offset_buff)?;
+        bytes
+            .get(index..index.saturating_add(len))
+            .ok_or(DecodeError::CantReadField)?
+    };
+
     match valtype {
-        ifd::Type::Byte => {
-            let val_buff = if count <= 4 {
-                &offset_buff[..count]
-            } else {
-                let index = endianness.read_u32(offset_buff).unwrap() as usize;
-                bytes
-                    .get(index..index + count)
-                    .ok_or(DecodeError::CantReadField)?
-            };
-            Ok(ifd::Values::Bytes(Vec::from(val_buff)))
-        }
-        ifd::Type::Short => {
-            let val_buff = if count <= 2 {
-                &offset_buff[..count * 2]
-            } else {
-                let index = endianness.read_u32(offset_buff).unwrap() as usize;
-                bytes
-                    .get(index..index + count * 2)
-                    .ok_or(DecodeError::CantReadField)?
-            };
-            Ok(ifd::Values::Shorts(
-                val_buff
-                    .chunks_exact(2)
-                    .into_iter()
-                    .map(|chunk| endianness.read_u16(chunk).unwrap())
-                    .collect(),
-            ))
-        }
-        ifd::Type::Long => {
-            let val_buff = if count <= 1 {
-                offset_buff
-            } else {
-                let index = endianness.read_u32(offset_buff).unwrap() as usize;
-                bytes
-                    .get(index..index + count * 4)
-                    .ok_or(DecodeError::CantReadField)?
-            };
-            Ok(ifd::Values::Longs(
-                val_buff
-                    .chunks_exact(4)
-                    .into_iter()
-                    .map(|chunk| endianness.read_u32(chunk).unwrap())
-                    .collect(),
-            ))
-        }
-        ifd::Type::Rational => {
-            let index = endianness.read_u32(offset_buff).unwrap() as usize;
-            let mut longs = bytes
-                .get(index..index + count * 8)
-                .map(|bytes| bytes.chunks_exact(4))
-                .map(|chunks| chunks.map(|chunk| endianness.read_u32(chunk).unwrap()))
-                .ok_or(DecodeError::CantReadField)?;
-            let mut rats = Vec::new();
-            rats.reserve_exact(count);
-            while let (Some(numerator), Some(denominator)) = (longs.next(), longs.next()) {
-                rats.push(URational {
-                    numerator,
-                    denominator,
+        ifd::Type::Byte => Ok(ifd::Values::Bytes(Vec::from(val_buff))),
+        ifd::Type::Short => Ok(ifd::Values::Shorts(
+            val_buff
+                .chunks_exact(2)
+                .map(|chunk| endianness.read_u16(chunk).unwrap())
+                .collect(),
+        )),
+        ifd::Type::Long => Ok(ifd::Values::Longs(
+            val_buff
+                .chunks_exact(4)
+                .map(|chunk| endianness.read_u32(chunk).unwrap())
+                .collect(),
+        )),
+        ifd::Type::Rational => Ok(ifd::Values::Rationals(
+            val_buff
+                .chunks_exact(8)
+                .map(|chunk| URational {
+                    numerator: endianness.read_u32(&chunk[..4]).unwrap(),
+                    denominator: endianness.read_u32(&chunk[4..]).unwrap(),
                 })
-            }
-            Ok(ifd::Values::Rationals(rats))
-        }
-        ifd::Type::ASCII => {
-            let index = endianness.read_u32(offset_buff).unwrap() as usize;
-            if count <= 1 {
-                bytes
-                    .get(index..)
-                    .and_then(|str_bytes| CStr::from_bytes_until_nul(str_bytes).ok())
-                    .and_then(|cstr| cstr.to_str().ok()) // TODO: Decode to exact CStrings
-                    .map(|str| str.to_owned())
-                    .map(ifd::Values::ASCII)
-                    .ok_or(DecodeError::CantReadField)
-            } else {
-                todo!("support decoding multiple strings")
-            }
-        }
+                .collect(),
+        )),
+        ifd::Type::SByte => Ok(ifd::Values::SBytes(
+            val_buff.iter().map(|&byte| byte as i8).collect(),
+        )),
+        ifd::Type::Undefined => Ok(ifd::Values::Undefined(Vec::from(val_buff))),
+        ifd::Type::SShort => Ok(ifd::Values::SShorts(
+            val_buff
+                .chunks_exact(2)
+                .map(|chunk| endianness.read_i16(chunk).unwrap())
+                .collect(),
+        )),
+        ifd::Type::SLong => Ok(ifd::Values::SLongs(
+            val_buff
+                .chunks_exact(4)
+                .map(|chunk| endianness.read_i32(chunk).unwrap())
+                .collect(),
+        )),
+        ifd::Type::SRational => Ok(ifd::Values::SRationals(
+            val_buff
+                .chunks_exact(8)
+                .map(|chunk| SRational {
+                    numerator: endianness.read_i32(&chunk[..4]).unwrap(),
+                    denominator: endianness.read_i32(&chunk[4..]).unwrap(),
+                })
+                .collect(),
+        )),
+        ifd::Type::Float => Ok(ifd::Values::Floats(
+            val_buff
+                .chunks_exact(4)
+                .map(|chunk| endianness.read_f32(chunk).unwrap())
+                .collect(),
+        )),
+        ifd::Type::Double => Ok(ifd::Values::Doubles(
+            val_buff
+                .chunks_exact(8)
+                .map(|chunk| endianness.read_f64(chunk).unwrap())
+                .collect(),
+        )),
+        // Multiple strings are separated by NULs, so keep everything up to
+        // the final NUL, without any NULs padding the end
+        ifd::Type::ASCII => val_buff
+            .iter()
+            .rposition(|&byte| byte == 0)
+            .map(|end| {
+                let len = val_buff[..end]
+                    .iter()
+                    .rposition(|&byte| byte != 0)
+                    .map_or(0, |last| last + 1);
+                &val_buff[..len]
+            })
+            .and_then(|str| std::str::from_utf8(str).ok()) // TODO: Decode to exact CStrings
+            .map(|str| str.to_owned())
+            .map(ifd::Values::ASCII)
+            .ok_or(DecodeError::CantReadField),
+        ifd::Type::Long8 => Ok(ifd::Values::Long8s(
+            val_buff
+                .chunks_exact(8)
+                .map(|chunk| endianness.read_u64(chunk).unwrap())
+                .collect(),
+        )),
+        ifd::Type::Ifd8 => Ok(ifd::Values::Ifd8s(
+            val_buff
+                .chunks_exact(8)
+                .map(|chunk| endianness.read_u64(chunk).unwrap())
+                .collect(),
+        )),
+    }
+}
+
+/// Reads a 4 byte offset from a classic TIFF or an 8 byte offset from a
+/// BigTIFF.
+pub(crate) fn read_offset(
+    endianness: Endianness,
+    format: ifd::Format,
+    buff: &[u8],
+) -> Result<usize, DecodeError> {
+    let offset = match format {
+        ifd::Format::Classic => endianness.read_u32(buff).map(u64::from),
+        ifd::Format::BigTiff => endianness.read_u64(buff),
     }
+    .map_err(|_| DecodeError::CantReadField)?;
+    offset.try_into().map_err(|_| DecodeError::CantReadField)
 }
 
 pub(crate) fn find(fields: &[ifd::Entry], tag: ifd::Tag) -> Option<&ifd::Values> {
@@ -137,6 +175,32 @@ pub(crate) fn read_single_short(values: &ifd::Values) -> Result<Short, DecodeErr
     }
 }
 
+// TODO: Use DecodeError::InvalidTypeForTag
+pub(crate) fn read_shorts(values: &ifd::Values) -> Result<&[Short], DecodeError> {
+    match values {
+        ifd::Values::Shorts(shorts) => Ok(shorts),
+        _ => Err(DecodeError::TiffFieldError),
+    }
+}
+
+// TODO: Use DecodeError::InvalidTypeForTag
+/// Reads an ASCII field, including the NULs between multiple strings.
+pub(crate) fn read_ascii(values: &ifd::Values) -> Result<&str, DecodeError> {
+    match values {
+        ifd::Values::ASCII(string) => Ok(string),
+        _ => Err(DecodeError::TiffFieldError),
+    }
+}
+
+// TODO: Use DecodeError::InvalidTypeForTag
+/// Reads the NUL separated strings of an ASCII field.
+pub(crate) fn read_strings(values: &ifd::Values) -> Result<Vec<&str>, DecodeError> {
+    match values {
+        ifd::Values::ASCII(string) => Ok(string.split('\0').collect()),
+        _ => Err(DecodeError::TiffFieldError),
+    }
+}
+
 // TODO: Use DecodeError::InvalidTagValueCount and DecodeError::InvalidTypeForTag
 /// Reads a single short or long
 pub(crate) fn read_single(values: &ifd::Values) -> Result<Long, DecodeError> {
@@ -163,11 +227,40 @@ pub(crate) fn read_single_rational(values: &ifd::Values) -> Result<URational, De
     }
 }
 
-/// Reads shorts or longs
+/// Reads shorts, longs or long8s
 pub(crate) fn as_usizes(values: ifd::Values) -> Result<Vec<usize>, DecodeError> {
     match values {
         ifd::Values::Shorts(shorts) => Ok(shorts.into_iter().map(usize::from).collect()),
         ifd::Values::Longs(longs) => Ok(longs.into_iter().map(|long| long as usize).collect()),
+        ifd::Values::Long8s(long8s) => long8s
+            .into_iter()
+            .map(|long8| long8.try_into().map_err(|_| DecodeError::TiffFieldError))
+            .collect(),
+        _ => Err(DecodeError::TiffFieldError),
+    }
+}
+
+/// Reads numeric values of any type as doubles, such as the minimum and
+/// maximum sample values which are stored in the format of the samples.
+pub(crate) fn read_f64s(values: &ifd::Values) -> Result<Vec<f64>, DecodeError> {
+    match values {
+        ifd::Values::Bytes(bytes) => Ok(bytes.iter().map(|&byte| byte.into()).collect()),
+        ifd::Values::Shorts(shorts) => Ok(shorts.iter().map(|&short| short.into()).collect()),
+        ifd::Values::Longs(longs) => Ok(longs.iter().map(|&long| long.into()).collect()),
+        ifd::Values::Long8s(long8s) => Ok(long8s.iter().map(|&long8| long8 as f64).collect()),
+        ifd::Values::Rationals(rationals) => Ok(rationals
+            .iter()
+            .map(|rational| rational.numerator as f64 / rational.denominator as f64)
+            .collect()),
+        ifd::Values::SBytes(sbytes) => Ok(sbytes.iter().map(|&sbyte| sbyte.into()).collect()),
+        ifd::Values::SShorts(sshorts) => Ok(sshorts.iter().map(|&sshort| sshort.into()).collect()),
+        ifd::Values::SLongs(slongs) => Ok(slongs.iter().map(|&slong| slong.into()).collect()),
+        ifd::Values::SRationals(srationals) => Ok(srationals
+            .iter()
+            .map(|rational| rational.numerator as f64 / rational.denominator as f64)
+            .collect()),
+        ifd::Values::Floats(floats) => Ok(floats.iter().map(|&float| float.into()).collect()),
+        ifd::Values::Doubles(doubles) => Ok(doubles.clone()),
         _ => Err(DecodeError::TiffFieldError),
     }
 }
@@ -0,0 +1,159 @@
+use super::{read_samples_per_pixel, tiff_field, DecodeError, ImageInfo, YCbCrImage};
+use crate::{colors, ifd, Image};
+
+/// The luma coefficients of ITU-R BT.601, which are used if the file does not
+/// specify any.
+const DEFAULT_COEFFICIENTS: [f32; 3] = [0.299, 0.587, 0.114];
+const DEFAULT_REFERENCE_BLACK_WHITE: [f32; 6] = [0.0, 255.0, 128.0, 255.0, 128.0, 255.0];
+
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    fields: Vec<ifd::Entry>,
+    info: ImageInfo,
+) -> Result<YCbCrImage, DecodeError> {
+    let samples_per_pixel = read_samples_per_pixel(&fields)?;
+    if samples_per_pixel != 3 {
+        return Err(DecodeError::UnsupportedSamplesPerPixel(samples_per_pixel));
+    }
+    let bits_per_sample = tiff_field::find_required(&fields, ifd::Tag::BitsPerSample)
+        .and_then(tiff_field::read_shorts)?;
+    if bits_per_sample != [8, 8, 8] {
+        return Err(DecodeError::UnsupportedBitsPerSample(
+            bits_per_sample.to_vec(),
+        ));
+    }
+    let (horizontal, vertical) = match tiff_field::find(&fields, ifd::Tag::YCbCrSubSampling) {
+        Some(values) => match tiff_field::read_shorts(values)? {
+            // TODO: Use DecodeError::InvalidTagValues
+            &[horizontal @ (1 | 2 | 4), vertical @ (1 | 2 | 4)] if vertical <= horizontal => {
+                (horizontal, vertical)
+            }
+            _ => return Err(DecodeError::TiffFieldError),
+        },
+        None => (2, 2),
+    };
+    let positioning = match tiff_field::find(&fields, ifd::Tag::YCbCrPositioning) {
+        Some(values) => tiff_field::read_single_short(values).and_then(|positioning| {
+            // TODO: Use DecodeError::InvalidTagValues
+            ifd::tags::YCbCrPositioning::from_repr(positioning).ok_or(DecodeError::TiffFieldError)
+        })?,
+        None => Default::default(),
+    };
+    let coefficients = match tiff_field::find(&fields, ifd::Tag::YCbCrCoefficients) {
+        Some(values) => read_rationals(values)?,
+        None => DEFAULT_COEFFICIENTS,
+    };
+    let reference_black_white = match tiff_field::find(&fields, ifd::Tag::ReferenceBlackWhite) {
+        Some(values) => read_rationals(values)?,
+        None => DEFAULT_REFERENCE_BLACK_WHITE,
+    };
+    if coefficients[1] == 0.0
+        || reference_black_white
+            .chunks_exact(2)
+            .any(|black_white| black_white[0] == black_white[1])
+    {
+        return Err(DecodeError::TiffFieldError);
+    }
+
+    let (horizontal, vertical) = (horizontal as usize, vertical as usize);
+    let [luma, cb, cr] = match info.planar_configuration {
+        ifd::tags::PlanarConfiguration::Chunky => {
+            split_data_units(&info, bytes, (horizontal, vertical))?
+        }
+        // The chroma planes are stored subsampled
+        ifd::tags::PlanarConfiguration::Planar => [
+            info.read_plane(bytes, 8, 0, 3, (1, 1))?,
+            info.read_plane(bytes, 8, 1, 3, (horizontal, vertical))?,
+            info.read_plane(bytes, 8, 2, 3, (horizontal, vertical))?,
+        ],
+    };
+
+    Ok(YCbCrImage {
+        width: info.width,
+        height: info.height,
+        subsampling: (horizontal as u16, vertical as u16),
+        positioning,
+        coefficients,
+        reference_black_white,
+        luma,
+        cb,
+        cr,
+    })
+}
+
+/// Reads chunky data, where each data unit holds the luma samples of a
+/// subsampled block of pixels followed by its Cb and Cr samples, into
+/// separate planes.
+fn split_data_units(
+    info: &ImageInfo,
+    bytes: &[u8],
+    (horizontal, vertical): (usize, usize),
+) -> Result<[Vec<u8>; 3], DecodeError> {
+    let luma_per_unit = horizontal * vertical;
+    let units_across = info.width.div_ceil(horizontal);
+    let data = info.read_plane(bytes, (luma_per_unit + 2) * 8, 0, 1, (horizontal, vertical))?;
+
+    let mut luma = vec![0; info.width * info.height];
+    let mut cb = Vec::with_capacity(data.len() / (luma_per_unit + 2));
+    let mut cr = Vec::with_capacity(data.len() / (luma_per_unit + 2));
+    for (unit_inx, unit) in data.chunks_exact(luma_per_unit + 2).enumerate() {
+        let first_x = (unit_inx % units_across) * horizontal;
+        let first_y = (unit_inx / units_across) * vertical;
+        for (sample_inx, &sample) in unit[..luma_per_unit].iter().enumerate() {
+            // Units past the right and bottom edges are padded
+            let x = first_x + sample_inx % horizontal;
+            let y = first_y + sample_inx / horizontal;
+            if x < info.width && y < info.height {
+                luma[y * info.width + x] = sample;
+            }
+        }
+        cb.push(unit[luma_per_unit]);
+        cr.push(unit[luma_per_unit + 1]);
+    }
+    Ok([luma, cb, cr])
+}
+
+fn read_rationals<const N: usize>(values: &ifd::Values) -> Result<[f32; N], DecodeError> {
+    match values {
+        // TODO: Use DecodeError::InvalidTagValueCount
+        ifd::Values::Rationals(rationals) if rationals.len() == N => {
+            Ok(std::array::from_fn(|inx| {
+                rationals[inx].numerator as f32 / rationals[inx].denominator as f32
+            }))
+        }
+        // TODO: Use DecodeError::InvalidTypeForTag
+        _ => Err(DecodeError::TiffFieldError),
+    }
+}
+
+pub(crate) fn to_rgb(image: &YCbCrImage) -> Image<colors::RGB> {
+    let (horizontal, vertical) = (image.subsampling.0 as usize, image.subsampling.1 as usize);
+    let units_across = image.width.div_ceil(horizontal);
+    let [luma_red, luma_green, luma_blue] = image.coefficients;
+    let [y_black, y_white, cb_black, cb_white, cr_black, cr_white] = image.reference_black_white;
+
+    let pixels = image
+        .luma
+        .iter()
+        .enumerate()
+        .map(|(inx, &luma)| {
+            let unit_inx =
+                (inx / image.width / vertical) * units_across + (inx % image.width) / horizontal;
+            // Scale the samples to a full range luma and zero centered chroma
+            let y = (luma as f32 - y_black) * 255.0 / (y_white - y_black);
+            let cb = (image.cb[unit_inx] as f32 - cb_black) * 127.0 / (cb_white - cb_black);
+            let cr = (image.cr[unit_inx] as f32 - cr_black) * 127.0 / (cr_white - cr_black);
+
+            let r = y + cr * (2.0 - 2.0 * luma_red);
+            let b = y + cb * (2.0 - 2.0 * luma_blue);
+            let g = (y - luma_blue * b - luma_red * r) / luma_green;
+            colors::RGB::new(to_sample(r), to_sample(g), to_sample(b))
+        })
+        .collect();
+
+    Image::new(pixels, image.width, image.height)
+}
+
+fn to_sample(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
@@ -1,19 +1,20 @@
-use std::{marker::PhantomData, slice::ChunksExact};
+use std::marker::PhantomData;
 
 use crate::{
     colors,
     compression::Compression,
     encode::{encode_ifds, private::EncodeResult},
     ifd,
-    types::{Short, URational},
+    types::Short,
     Image,
 };
 
 use super::{
     buffer::TiffEncodeBuffer,
     compression::BitPacker,
+    layout::encode_image_data,
     private::{IfdInfo, ImageEncoderImpl},
-    EncodeEndianness, ImageEncoder,
+    EncodeEndianness, ImageEncoder, Layout,
 };
 
 pub trait PhotometricInterpretation: private::PhotometricInterpretationImpl {}
@@ -33,6 +34,7 @@ where
     image: &'a Image<colors::Bilevel>,
     image_compressor: C,
     photo_interp: P,
+    layout: Layout,
     endianness: PhantomData<E>,
 }
 
@@ -47,9 +49,16 @@ where
             image,
             image_compressor: compression,
             photo_interp,
+            layout: Layout::default(),
             endianness: PhantomData,
         }
     }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
 }
 
 impl<'a, E, C, P> ImageEncoder for BilevelImageEncoder<'a, E, C, P>
@@ -69,19 +78,17 @@ where
     type Endianness = E;
 
     fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
-        let EncodeResult {
-            image_strip_offsets,
-            image_strip_bytecounts,
-        } = encode_bilevel_img(
+        let image_data = encode_bilevel_img(
             wrt,
-            self.image.rows(),
+            self.image,
+            self.layout,
             self.photo_interp,
             &self.image_compressor,
         );
 
         let ifd_inx = wrt.align_and_get_len();
 
-        let ifd_entries = [
+        let mut ifd_entries = vec![
             ifd::Entry::new(
                 ifd::Tag::ImageWidth,
                 ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
@@ -98,38 +105,10 @@ where
                 ifd::Tag::PhotometricInterpretation,
                 ifd::Values::Shorts(vec![self.photo_interp.tag() as Short]),
             ),
-            ifd::Entry::new(
-                ifd::Tag::StripOffsets,
-                ifd::Values::Shorts(image_strip_offsets),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::RowsPerStrip,
-                ifd::Values::Shorts(vec![self.image.height().try_into().unwrap()]),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::StripByteCounts,
-                ifd::Values::Longs(image_strip_bytecounts),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::XResolution,
-                ifd::Values::Rationals(vec![URational::new(1, 1)]),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::YResolution,
-                ifd::Values::Rationals(vec![URational::new(1, 1)]),
-            ),
         ];
-        let entry_count = ifd_entries.len();
-
-        debug_assert!(
-            ifd_entries
-                .iter()
-                .zip(ifd_entries.iter().skip(1))
-                .all(|(prev, next)| prev.tag() <= next.tag()),
-            "IFD entries are not sorted by tag"
-        );
-
-        encode_ifds(wrt, ifd_entries.into_iter());
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
 
         IfdInfo {
             inx: ifd_inx,
@@ -140,7 +119,8 @@ where
 
 fn encode_bilevel_img<E, C, P>(
     wrt: &mut TiffEncodeBuffer<E>,
-    rows: ChunksExact<'_, colors::Bilevel>,
+    image: &Image<colors::Bilevel>,
+    layout: Layout,
     photo_iterp: P,
     image_compressor: &C,
 ) -> EncodeResult
@@ -149,19 +129,12 @@ where
     C: Compression<colors::Bilevel>,
     P: PhotometricInterpretation,
 {
-    let row_inx = wrt.align_and_get_len();
-
-    image_compressor.encode(
-        wrt,
-        rows.flat_map(|row| {
-            BitPacker::new(row.iter().map(|pixel| photo_iterp.encode_pixel(*pixel)))
-        }),
-    );
-
-    EncodeResult {
-        image_strip_offsets: vec![row_inx.try_into().unwrap()],
-        image_strip_bytecounts: vec![(wrt.len() - row_inx).try_into().unwrap()],
-    }
+    encode_image_data(wrt, image, layout, 1, image_compressor, |row| {
+        BitPacker::new(
+            row.iter()
+                .map(move |pixel| photo_iterp.encode_pixel(*pixel)),
+        )
+    })
 }
 
 pub(crate) mod private {
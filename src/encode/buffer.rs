@@ -1,42 +1,66 @@
-use std::{io::Write, iter::repeat, marker::PhantomData, mem::size_of};
+use std::{io::Write, iter::repeat_n, marker::PhantomData};
 
 use byteorder::WriteBytesExt;
 
 use crate::{
     ifd,
-    types::{Byte, Long, Short, URational},
+    types::{Byte, Long, Short},
 };
 
 use super::{image_header, EncodeEndianness};
 
 pub struct TiffEncodeBuffer<E: EncodeEndianness> {
     bytes: Vec<u8>,
+    format: ifd::Format,
+    /// Entries to add to the next IFD, along with the entries of its image
+    extra_entries: Vec<ifd::RawEntry>,
     phantom: PhantomData<E>,
 }
 
 pub(crate) struct TiffHeaderEncodeBuffer<'a, E: EncodeEndianness>(
-    &'a mut [u8; image_header::LEN],
+    &'a mut [u8],
+    ifd::Format,
     PhantomData<E>,
 );
 
-pub(crate) struct IFDEncodeBuffer<'a, E: EncodeEndianness>(&'a mut [u8], PhantomData<E>);
+pub(crate) struct IFDEncodeBuffer<'a, E: EncodeEndianness>(
+    &'a mut [u8],
+    ifd::Format,
+    PhantomData<E>,
+);
 
 pub(crate) struct IFDEntryEncodeBuffer<'a, E: EncodeEndianness>(
-    &'a mut [u8; ifd::Entry::LEN],
+    &'a mut [u8],
+    ifd::Format,
     PhantomData<E>,
 );
 
 impl<E: EncodeEndianness> TiffEncodeBuffer<E> {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(format: ifd::Format) -> Self {
         let mut ret = Self {
             bytes: vec![E::get_sentinel(), E::get_sentinel()], // Endianness
+            format,
+            extra_entries: Vec::new(),
             phantom: PhantomData,
         };
-        // Magic number
-        ret.append_short(42);
+        match format {
+            ifd::Format::Classic => {
+                // Magic number
+                ret.append_short(42);
+            }
+            ifd::Format::BigTiff => {
+                // Magic number
+                ret.append_short(43);
+                // Byte size of offsets
+                ret.append_short(8);
+                // Reserved
+                ret.append_short(0);
+            }
+        }
         // Byte offset of first IFD (immediately after the header). This can be
         // overwritten later if necessary.
-        ret.append_long(8);
+        let header_len = image_header::len(format);
+        ret.append_offset(header_len as u64);
         ret
     }
 
@@ -44,9 +68,14 @@ impl<E: EncodeEndianness> TiffEncodeBuffer<E> {
         self.bytes
     }
 
+    pub(crate) fn format(&self) -> ifd::Format {
+        self.format
+    }
+
     pub(crate) fn get_tiff_header(&mut self) -> TiffHeaderEncodeBuffer<'_, E> {
         TiffHeaderEncodeBuffer(
-            (&mut self.bytes[0..image_header::LEN]).try_into().unwrap(),
+            &mut self.bytes[0..image_header::len(self.format)],
+            self.format,
             PhantomData,
         )
     }
@@ -54,86 +83,66 @@ impl<E: EncodeEndianness> TiffEncodeBuffer<E> {
     pub(crate) fn append_new_ifd(&mut self, fields: usize) -> usize {
         let inx = self.align_and_get_len();
         // Write number of directory entries
-        self.append_short(fields.try_into().unwrap());
+        match self.format {
+            ifd::Format::Classic => self.append_short(fields.try_into().unwrap()),
+            ifd::Format::BigTiff => self.append_long8(fields as u64),
+        }
         // Reserve space for directory fields
-        self.bytes.extend(repeat(0).take(fields * ifd::Entry::LEN));
+        self.bytes
+            .extend(repeat_n(0, fields * self.format.entry_len()));
         // Write next IFD offset
-        self.bytes.extend([0, 0, 0, 0]);
+        self.append_offset(0);
 
         inx
     }
 
+    /// Adds entries to write in the next IFD.
+    pub(crate) fn add_extra_entries<I: IntoIterator<Item = ifd::RawEntry>>(&mut self, entries: I) {
+        self.extra_entries.extend(entries)
+    }
+
+    /// Returns the entries to write in the next IFD, clearing them.
+    pub(crate) fn take_extra_entries(&mut self) -> Vec<ifd::RawEntry> {
+        std::mem::take(&mut self.extra_entries)
+    }
+
     pub(crate) fn get_ifd_at(&mut self, inx: usize, fields: usize) -> IFDEncodeBuffer<'_, E> {
-        let end = inx + ifd::get_len(fields);
-        IFDEncodeBuffer(&mut self.bytes[inx..end], PhantomData)
-    }
-
-    pub(crate) fn append_ifd_value(&mut self, ifd_value: &ifd::Values) -> [u8; 4] {
-        let mut offset = [0, 0, 0, 0];
-
-        match ifd_value {
-            ifd::Values::Bytes(bytes) => match bytes[..] {
-                [] | [_] | [_, _] | [_, _, _] | [_, _, _, _] => {
-                    (&mut offset[..]).write(&bytes).unwrap();
-                }
-                _ => {
-                    (&mut offset[..])
-                        .write_u32::<E>(self.align_and_get_len().try_into().unwrap())
-                        .unwrap();
-                    self.bytes.extend(bytes.iter());
-                }
-            },
-            ifd::Values::ASCII(string) => {
-                (&mut offset[..])
-                    .write_u32::<E>(self.align_and_get_len().try_into().unwrap())
-                    .unwrap();
-                self.bytes.extend(string.as_bytes());
-                // Termainting NUL char
-                self.append_byte(0);
-            }
-            ifd::Values::Shorts(shorts) => {
-                match shorts[..] {
-                    [] => (),
-                    [short] => (&mut offset[0..2]).write_u16::<E>(short).unwrap(),
-                    [short1, short2] => {
-                        (&mut offset[0..2]).write_u16::<E>(short1).unwrap();
-                        (&mut offset[2..4]).write_u16::<E>(short2).unwrap();
-                    }
-                    _ => {
-                        (&mut offset[..])
-                            .write_u32::<E>(self.align_and_get_len().try_into().unwrap())
-                            .unwrap();
-                        for short in shorts.iter() {
-                            self.append_short(*short);
-                        }
-                    }
-                };
-            }
-            ifd::Values::Longs(longs) => {
-                match longs[..] {
-                    [] => (),
-                    [long] => (&mut offset[..]).write_u32::<E>(long).unwrap(),
-                    _ => {
-                        (&mut offset[..])
-                            .write_u32::<E>(self.align_and_get_len().try_into().unwrap())
-                            .unwrap();
-                        for long in longs.iter() {
-                            self.append_long(*long);
-                        }
-                    }
-                };
-            }
-            ifd::Values::Rationals(rationals) => {
-                (&mut offset[..])
-                    .write_u32::<E>(self.align_and_get_len().try_into().unwrap())
-                    .unwrap();
-                for rat in rationals.iter() {
-                    self.append_urational(*rat);
-                }
-            }
+        let end = inx + self.format.ifd_len(fields);
+        IFDEncodeBuffer(&mut self.bytes[inx..end], self.format, PhantomData)
+    }
+
+    /// Writes the values if they do not fit in an entry's value offset field,
+    /// and returns the contents of that field.
+    pub(crate) fn append_ifd_value(&mut self, ifd_value: &ifd::Values) -> Vec<u8> {
+        if let ifd::Values::Long8s(_) | ifd::Values::Ifd8s(_) = ifd_value {
+            assert_eq!(
+                self.format,
+                ifd::Format::BigTiff,
+                "classic TIFF files can not be over 4 GiB"
+            );
         }
+        let mut value_bytes = ifd_value.to_bytes::<E>();
 
-        offset
+        if value_bytes.len() <= self.format.offset_len() {
+            // Values that fit are stored in the offset field itself
+            value_bytes.resize(self.format.offset_len(), 0);
+            value_bytes
+        } else {
+            let mut offset = Vec::new();
+            let value_inx = self.align_and_get_len() as u64;
+            match self.format {
+                ifd::Format::Classic => offset
+                    .write_u32::<E>(
+                        value_inx
+                            .try_into()
+                            .expect("classic TIFF files can not be over 4 GiB"),
+                    )
+                    .unwrap(),
+                ifd::Format::BigTiff => offset.write_u64::<E>(value_inx).unwrap(),
+            }
+            self.bytes.extend(value_bytes);
+            offset
+        }
     }
 
     pub(crate) fn append_byte(&mut self, byte: Byte) {
@@ -148,9 +157,21 @@ impl<E: EncodeEndianness> TiffEncodeBuffer<E> {
         self.bytes.write_u32::<E>(long).unwrap()
     }
 
-    pub(crate) fn append_urational(&mut self, urational: URational) {
-        self.bytes.write_u32::<E>(urational.numerator).unwrap();
-        self.bytes.write_u32::<E>(urational.denominator).unwrap()
+    pub(crate) fn append_long8(&mut self, long8: u64) {
+        self.bytes.write_u64::<E>(long8).unwrap()
+    }
+
+    /// Appends a 4 byte offset to a classic TIFF or an 8 byte offset to a
+    /// BigTIFF.
+    pub(crate) fn append_offset(&mut self, offset: u64) {
+        match self.format {
+            ifd::Format::Classic => self.append_long(
+                offset
+                    .try_into()
+                    .expect("classic TIFF files can not be over 4 GiB"),
+            ),
+            ifd::Format::BigTiff => self.append_long8(offset),
+        }
     }
 
     pub(crate) fn extend_bytes<I: Iterator<Item = Byte>>(&mut self, iter: I) {
@@ -173,40 +194,163 @@ impl<E: EncodeEndianness> TiffEncodeBuffer<E> {
     }
 }
 
+/// Writes a 4 byte offset for a classic TIFF or an 8 byte offset for a
+/// BigTIFF to the start of `buff`.
+fn write_offset<E: EncodeEndianness>(mut buff: &mut [u8], format: ifd::Format, offset: u64) {
+    match format {
+        ifd::Format::Classic => buff
+            .write_u32::<E>(
+                offset
+                    .try_into()
+                    .expect("classic TIFF files can not be over 4 GiB"),
+            )
+            .unwrap(),
+        ifd::Format::BigTiff => buff.write_u64::<E>(offset).unwrap(),
+    }
+}
+
 impl<'a, E: EncodeEndianness> TiffHeaderEncodeBuffer<'a, E> {
-    pub(crate) fn set_first_ifd_offset(&mut self, offset: Long) {
-        (&mut self.0[4..8]).write_u32::<E>(offset).unwrap()
+    pub(crate) fn set_first_ifd_offset(&mut self, offset: u64) {
+        let buff_inx = image_header::len(self.1) - self.1.offset_len();
+        write_offset::<E>(&mut self.0[buff_inx..], self.1, offset)
     }
 }
 
 impl<'a, E: EncodeEndianness> IFDEncodeBuffer<'a, E> {
     pub(crate) fn get_entry(&mut self, entry_num: usize) -> IFDEntryEncodeBuffer<'_, E> {
-        let start = ifd::ENTRY_COUNT_LEN + entry_num * ifd::Entry::LEN;
-        let end = start + ifd::Entry::LEN;
-        IFDEntryEncodeBuffer((&mut self.0[start..end]).try_into().unwrap(), PhantomData)
+        let start = self.1.entry_count_len() + entry_num * self.1.entry_len();
+        let end = start + self.1.entry_len();
+        IFDEntryEncodeBuffer(&mut self.0[start..end], self.1, PhantomData)
     }
 
-    pub(crate) fn set_next_ifd_offset(&mut self, offset: Long) {
-        let buff_inx = self.0.len() - size_of::<Long>();
-        (&mut self.0[buff_inx..]).write_u32::<E>(offset).unwrap()
+    pub(crate) fn set_next_ifd_offset(&mut self, offset: u64) {
+        let buff_inx = self.0.len() - self.1.offset_len();
+        write_offset::<E>(&mut self.0[buff_inx..], self.1, offset)
     }
 }
 
 impl<'a, E: EncodeEndianness> IFDEntryEncodeBuffer<'a, E> {
-    pub(crate) fn set_all(&mut self, entry: &ifd::Entry, value_offset: [u8; 4]) {
+    pub(crate) fn set_all(&mut self, entry: &ifd::RawEntry, value_offset: &[u8]) {
         // Write tag
-        (&mut self.0[0..2])
-            .write_u16::<E>(entry.tag() as u16)
-            .unwrap();
+        (&mut self.0[0..2]).write_u16::<E>(entry.tag()).unwrap();
         // Write value type
         (&mut self.0[2..4])
-            .write_u16::<E>(entry.values().field_type_tag() as u16)
+            .write_u16::<E>(entry.field_type())
             .unwrap();
         // Write number of values
-        (&mut self.0[4..8])
-            .write_u32::<E>(entry.values().num_values())
+        let num_values = entry.values().num_values();
+        match self.1 {
+            ifd::Format::Classic => (&mut self.0[4..8])
+                .write_u32::<E>(num_values.try_into().unwrap())
+                .unwrap(),
+            ifd::Format::BigTiff => (&mut self.0[4..12]).write_u64::<E>(num_values).unwrap(),
+        }
+        // Write values or offset to values
+        let value_offset_inx = 4 + self.1.offset_len();
+        (&mut self.0[value_offset_inx..])
+            .write_all(value_offset)
             .unwrap();
-        // Write number of values
-        (&mut self.0[8..12]).write(&value_offset).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+    use byteordered::Endianness;
+
+    use super::TiffEncodeBuffer;
+    use crate::{
+        decode::tiff_field,
+        encode::EncodeEndianness,
+        ifd,
+        types::{SRational, URational},
+    };
+
+    /// Values of every field type, with one value that fits in an entry and
+    /// several that do not.
+    fn values_of_each_type() -> Vec<ifd::Values> {
+        vec![
+            ifd::Values::Bytes(vec![0x12]),
+            ifd::Values::Bytes((0..9).collect()),
+            ifd::Values::ASCII("abc".to_string()),
+            ifd::Values::ASCII("a longer string".to_string()),
+            ifd::Values::Shorts(vec![0x1234]),
+            ifd::Values::Shorts(vec![0x1234, 0x5678, 0x9ABC, 0xDEF0, 1]),
+            ifd::Values::Longs(vec![0x1234_5678]),
+            ifd::Values::Longs(vec![0x1234_5678, 0x9ABC_DEF0, 1]),
+            ifd::Values::Rationals(vec![URational::new(1, 3)]),
+            ifd::Values::Rationals(vec![URational::new(1, 3), URational::new(0x1234_5678, 7)]),
+            ifd::Values::SBytes(vec![-2]),
+            ifd::Values::SBytes(vec![-128, -1, 0, 1, 127, -3, -4, -5, -6]),
+            ifd::Values::SShorts(vec![-0x1234]),
+            ifd::Values::SShorts(vec![-0x1234, 0x5678, i16::MIN, i16::MAX, -1]),
+            ifd::Values::SLongs(vec![-0x1234_5678]),
+            ifd::Values::SLongs(vec![-0x1234_5678, i32::MIN, i32::MAX]),
+            ifd::Values::SRationals(vec![SRational {
+                numerator: -1,
+                denominator: 3,
+            }]),
+            ifd::Values::SRationals(vec![
+                SRational {
+                    numerator: -1,
+                    denominator: 3,
+                },
+                SRational {
+                    numerator: 0x1234_5678,
+                    denominator: -7,
+                },
+            ]),
+            ifd::Values::Floats(vec![-1.5]),
+            ifd::Values::Floats(vec![-1.5, 0.1, f32::MAX]),
+            ifd::Values::Doubles(vec![-1.5]),
+            ifd::Values::Doubles(vec![-1.5, 0.1, f64::MIN_POSITIVE]),
+            ifd::Values::Undefined(vec![0xFF]),
+            ifd::Values::Undefined((0..9).rev().collect()),
+        ]
+    }
+
+    fn assert_round_trip<E: EncodeEndianness>(
+        endianness: Endianness,
+        format: ifd::Format,
+        values: &ifd::Values,
+    ) {
+        let mut wrt = TiffEncodeBuffer::<E>::new(format);
+        let header_len = wrt.len();
+        let value_offset = wrt.append_ifd_value(values);
+        let value_len = values.num_values() as usize * values.field_type_tag().size();
+        // Only values that don't fit in the entry are written after it
+        assert_eq!(
+            wrt.len() > header_len,
+            value_len > format.offset_len(),
+            "{values:?}"
+        );
+
+        let read = tiff_field::read_values(
+            &wrt.to_bytes(),
+            endianness,
+            format,
+            values.field_type_tag(),
+            values.num_values() as usize,
+            &value_offset,
+        )
+        .unwrap();
+        assert_eq!(&read, values);
+    }
+
+    #[test]
+    fn field_types_round_trip() {
+        for format in [ifd::Format::Classic, ifd::Format::BigTiff] {
+            for values in values_of_each_type() {
+                assert_round_trip::<LittleEndian>(Endianness::Little, format, &values);
+                assert_round_trip::<BigEndian>(Endianness::Big, format, &values);
+            }
+        }
+        for long8s in [
+            ifd::Values::Long8s(vec![0x0102_0304_0506_0708]),
+            ifd::Values::Long8s(vec![0x0102_0304_0506_0708, u64::MAX]),
+        ] {
+            assert_round_trip::<LittleEndian>(Endianness::Little, ifd::Format::BigTiff, &long8s);
+            assert_round_trip::<BigEndian>(Endianness::Big, ifd::Format::BigTiff, &long8s);
+        }
     }
 }
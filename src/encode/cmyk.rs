@@ -0,0 +1,228 @@
+use std::marker::PhantomData;
+
+use crate::{
+    colors,
+    compression::Compression,
+    encode::{encode_ifds, private::EncodeResult},
+    ifd,
+    types::Short,
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::{encode_image_data, encode_planar_image_data},
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout, PlanarConfiguration,
+};
+
+pub struct CMYKImageEncoder<'a, E, C>
+where
+    C: Compression<colors::CMYK>,
+{
+    image: &'a Image<colors::CMYK>,
+    image_compressor: C,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    ink_names: Option<[String; 4]>,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::CMYK>> CMYKImageEncoder<'a, E, C> {
+    pub fn new(image: &'a Image<colors::CMYK>, compression: C) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            layout: Layout::default(),
+            planar_configuration: PlanarConfiguration::default(),
+            ink_names: None,
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets whether the samples of each pixel are stored together or in
+    /// separate planes.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
+
+    /// Sets the names of the cyan, magenta, yellow and black inks.
+    pub fn with_ink_names(mut self, ink_names: [String; 4]) -> Self {
+        self.ink_names = Some(ink_names);
+        self
+    }
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::CMYK>> ImageEncoder
+    for CMYKImageEncoder<'a, E, C>
+{
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::CMYK>> ImageEncoderImpl
+    for CMYKImageEncoder<'a, E, C>
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_cmyk_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.planar_configuration,
+            &self.image_compressor,
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::BitsPerSample,
+                ifd::Values::Shorts(vec![8, 8, 8, 8]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![
+                    ifd::tags::PhotometricInterpretation::Separated as Short,
+                ]),
+            ),
+            ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![4])),
+            ifd::Entry::new(
+                ifd::Tag::PlanarConfiguration,
+                ifd::Values::Shorts(vec![self.planar_configuration as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::InkSet,
+                ifd::Values::Shorts(vec![ifd::tags::InkSet::Cmyk as Short]),
+            ),
+            ifd::Entry::new(ifd::Tag::NumberOfInks, ifd::Values::Shorts(vec![4])),
+        ];
+        if let Some(ink_names) = &self.ink_names {
+            ifd_entries.push(ifd::Entry::new(
+                ifd::Tag::InkNames,
+                ifd::Values::ASCII(ink_names.join("\0")),
+            ));
+        }
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+fn encode_cmyk_img<C: Compression<colors::CMYK>, E: EncodeEndianness>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &Image<colors::CMYK>,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    image_compressor: &C,
+) -> EncodeResult {
+    match planar_configuration {
+        PlanarConfiguration::Chunky => {
+            encode_image_data(wrt, image, layout, 32, image_compressor, |row| {
+                row.iter()
+                    .flat_map(|pixel| [pixel.c, pixel.m, pixel.y, pixel.k])
+            })
+        }
+        PlanarConfiguration::Planar => encode_planar_image_data(
+            wrt,
+            image,
+            layout,
+            &[8, 8, 8, 8],
+            image_compressor,
+            |row, plane| {
+                row.iter()
+                    .map(move |pixel| [pixel.c, pixel.m, pixel.y, pixel.k][plane])
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::CMYKImageEncoder;
+    use crate::{
+        colors::CMYK,
+        decode::DecodedImage,
+        encode::{
+            compression::{NoCompression, PackBits},
+            ImageEncoder, PlanarConfiguration,
+        },
+        test_util::{assert_decodes_to, assert_same_pixels, decode_single},
+        Image,
+    };
+
+    fn cmyk_image() -> Image<CMYK> {
+        let pixels = (0..5 * 3)
+            .map(|i| CMYK::new(i * 17, 255 - i, i * 3, i * 11))
+            .collect();
+        Image::new(pixels, 5, 3)
+    }
+
+    #[test]
+    fn round_trip() {
+        let image = cmyk_image();
+        for planar_configuration in [PlanarConfiguration::Chunky, PlanarConfiguration::Planar] {
+            for encoded in [
+                CMYKImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                    .with_planar_configuration(planar_configuration)
+                    .encode(),
+                CMYKImageEncoder::<BigEndian, _>::new(&image, PackBits)
+                    .with_planar_configuration(planar_configuration)
+                    .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
+
+    #[test]
+    fn ink_names_round_trip() {
+        let image = cmyk_image();
+        let ink_names = ["Cyan", "Magenta", "Yellow", "Black"].map(String::from);
+        for encoded in [
+            CMYKImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                .with_ink_names(ink_names.clone())
+                .encode(),
+            CMYKImageEncoder::<BigEndian, _>::new(&image, NoCompression)
+                .with_ink_names(ink_names.clone())
+                .encode(),
+        ] {
+            let DecodedImage::CMYKImage(decoded) = decode_single(&encoded) else {
+                panic!("not a CMYK image")
+            };
+            assert_eq!(decoded.ink_names(), Some(&ink_names[..]));
+            assert_same_pixels(decoded.image(), &image);
+        }
+
+        let encoded = CMYKImageEncoder::<LittleEndian, _>::new(&image, NoCompression).encode();
+        let DecodedImage::CMYKImage(decoded) = decode_single(&encoded) else {
+            panic!("not a CMYK image")
+        };
+        assert_eq!(decoded.ink_names(), None);
+    }
+}
@@ -1,3 +1,9 @@
+//! Compression of image data, and packing of samples smaller than a byte.
+//!
+//! When packing samples with `HalfBytePacker` or `BitPacker`, each row of an
+//! image should be packed separately, so that every row starts on a new byte
+//! as required by the spec.
+
 pub use crate::compression::{Lzw, NoCompression, PackBits};
 
 use std::collections::HashMap;
@@ -202,13 +208,17 @@ pub(crate) fn lzw<E: EncodeEndianness, I: Iterator<Item = Byte>>(
     }
 
     fn get_bitcount(string_table: &HashMap<Vec<u8>, u16>) -> u8 {
-        match FIRST_CODE + string_table.len() as u16 {
+        get_code_bitcount(FIRST_CODE + string_table.len() as u16)
+    }
+
+    fn get_code_bitcount(next_code: u16) -> u8 {
+        match next_code {
             0..=255 => panic!(),
             256..=511 => 9,
             512..=1023 => 10,
             1024..=2047 => 11,
-            2048..=MAX_CODE => 12,
-            4095.. => panic!(),
+            2048..=4095 => 12,
+            4096.. => panic!(),
         }
     }
 
@@ -249,9 +259,15 @@ pub(crate) fn lzw<E: EncodeEndianness, I: Iterator<Item = Byte>>(
     }
 
     let code = get_code(&string_table, &curr);
-    let bitcount = get_bitcount(&string_table);
-    append_code(&mut bits, code, bitcount);
-    append_code(&mut bits, END_OF_INFORMATION_CODE, bitcount);
+    let next_code = FIRST_CODE + string_table.len() as u16;
+    append_code(&mut bits, code, get_code_bitcount(next_code));
+    // Decoders add a table entry for the last code before reading the
+    // EndOfInformation code, which may widen it
+    append_code(
+        &mut bits,
+        END_OF_INFORMATION_CODE,
+        get_code_bitcount(next_code + 1),
+    );
 
     wrt.extend_bytes(BitPacker::new(bits.into_iter()));
 }
@@ -303,3 +319,60 @@ pub(crate) mod sealed {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use byteorder::LittleEndian;
+
+    use crate::{
+        colors::{Bilevel, ColorMap, Grayscale4Bit, RGB},
+        encode::{
+            bilevel::{self, BilevelImageEncoder},
+            compression::NoCompression,
+            grayscale_4bit::{self, Grayscale4BitImageEncoder},
+            palette_color::PaletteColorImageEncoder,
+            ImageEncoder,
+        },
+        Image,
+    };
+
+    /// The image data of an uncompressed image with a single strip, which is
+    /// written right after the header.
+    fn strip_data(encoded: &[u8], len: usize) -> &[u8] {
+        &encoded[8..8 + len]
+    }
+
+    #[test]
+    fn rows_of_odd_widths_are_padded() {
+        use Bilevel::{Black, White};
+        let image = Image::new(vec![White, Black, White, Black, White, Black], 3, 2);
+        let encoded = BilevelImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            bilevel::BlackIsZero,
+        )
+        .encode();
+        assert_eq!(strip_data(&encoded, 2), [0b1010_0000, 0b0100_0000]);
+
+        let pixels = (1..=6).map(Grayscale4Bit::new).collect();
+        let image = Image::new(pixels, 3, 2);
+        let encoded = Grayscale4BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            grayscale_4bit::BlackIsZero,
+        )
+        .encode();
+        assert_eq!(strip_data(&encoded, 4), [0x12, 0x30, 0x45, 0x60]);
+
+        let mut color_map = ColorMap::new();
+        for i in 0..3 {
+            color_map.try_add_color(RGB::new(i, i, i));
+        }
+        let image = color_map
+            .try_new_indexed_image(&[0, 1, 2, 2, 1, 0], 3, 2)
+            .unwrap();
+        let encoded =
+            PaletteColorImageEncoder::<LittleEndian, _>::new(&image, NoCompression).encode();
+        assert_eq!(strip_data(&encoded, 4), [0x01, 0x20, 0x21, 0x00]);
+    }
+}
@@ -0,0 +1,389 @@
+use std::marker::PhantomData;
+
+use crate::{
+    bits::BitWriter, colors, compression::Compression, encode::encode_ifds, ifd, types::Short,
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::encode_image_data,
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout,
+};
+
+pub trait PhotometricInterpretation: private::PhotometricInterpretationImpl {}
+#[derive(Clone, Copy)]
+pub struct BlackIsZero;
+#[derive(Clone, Copy)]
+pub struct WhiteIsZero;
+
+impl PhotometricInterpretation for BlackIsZero {}
+impl PhotometricInterpretation for WhiteIsZero {}
+
+/// Encodes grayscale images with any bit depth from 1 to 32.
+pub struct GenericGrayscaleImageEncoder<'a, E, C, P = BlackIsZero>
+where
+    C: Compression<colors::GenericGrayscale>,
+    P: PhotometricInterpretation,
+{
+    image: &'a Image<colors::GenericGrayscale>,
+    image_compressor: C,
+    photo_interp: P,
+    bits_per_sample: u8,
+    layout: Layout,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E, C, P> GenericGrayscaleImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GenericGrayscale>,
+    P: PhotometricInterpretation,
+{
+    /// # Panics
+    ///
+    /// Panics if `bits_per_sample` is 0 or more than 32
+    pub fn new(
+        image: &'a Image<colors::GenericGrayscale>,
+        compression: C,
+        photo_interp: P,
+        bits_per_sample: u8,
+    ) -> Self {
+        assert!(
+            (1..=32).contains(&bits_per_sample),
+            "bits per sample must be from 1 to 32"
+        );
+        Self {
+            image,
+            image_compressor: compression,
+            photo_interp,
+            bits_per_sample,
+            layout: Layout::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+impl<'a, E, C, P> ImageEncoder for GenericGrayscaleImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GenericGrayscale>,
+    P: PhotometricInterpretation,
+{
+}
+
+impl<'a, E, C, P> ImageEncoderImpl for GenericGrayscaleImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GenericGrayscale>,
+    P: PhotometricInterpretation,
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let bits = self.bits_per_sample;
+        let photo_interp = self.photo_interp;
+        let image_data = encode_image_data(
+            wrt,
+            self.image,
+            self.layout,
+            bits as usize,
+            &self.image_compressor,
+            |row| {
+                let mut writer = BitWriter::new();
+                for pixel in row {
+                    writer.write_in_byte_order::<E>(
+                        photo_interp.encode_sample(pixel.0[0], bits),
+                        bits,
+                    );
+                }
+                writer.into_bytes().into_iter()
+            },
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::BitsPerSample,
+                ifd::Values::Shorts(vec![bits as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![self.photo_interp.tag() as Short]),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+/// Encodes RGB images with any bit depth from 1 to 32 for each sample.
+pub struct GenericRGBImageEncoder<'a, E, C>
+where
+    C: Compression<colors::GenericRGB>,
+{
+    image: &'a Image<colors::GenericRGB>,
+    image_compressor: C,
+    bits_per_sample: u8,
+    layout: Layout,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E, C> GenericRGBImageEncoder<'a, E, C>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GenericRGB>,
+{
+    /// # Panics
+    ///
+    /// Panics if `bits_per_sample` is 0 or more than 32
+    pub fn new(image: &'a Image<colors::GenericRGB>, compression: C, bits_per_sample: u8) -> Self {
+        assert!(
+            (1..=32).contains(&bits_per_sample),
+            "bits per sample must be from 1 to 32"
+        );
+        Self {
+            image,
+            image_compressor: compression,
+            bits_per_sample,
+            layout: Layout::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+impl<'a, E, C> ImageEncoder for GenericRGBImageEncoder<'a, E, C>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GenericRGB>,
+{
+}
+
+impl<'a, E, C> ImageEncoderImpl for GenericRGBImageEncoder<'a, E, C>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GenericRGB>,
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let bits = self.bits_per_sample;
+        let image_data = encode_image_data(
+            wrt,
+            self.image,
+            self.layout,
+            bits as usize * 3,
+            &self.image_compressor,
+            |row| {
+                let mut writer = BitWriter::new();
+                for sample in row.iter().flat_map(|pixel| pixel.0) {
+                    writer.write_in_byte_order::<E>(sample, bits);
+                }
+                writer.into_bytes().into_iter()
+            },
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::BitsPerSample,
+                ifd::Values::Shorts(vec![bits as Short; 3]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![ifd::tags::PhotometricInterpretation::RGB as Short]),
+            ),
+            ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![3])),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+pub(crate) mod private {
+    use super::{BlackIsZero, WhiteIsZero};
+    use crate::ifd;
+
+    pub trait PhotometricInterpretationImpl: Copy {
+        fn encode_sample(&self, sample: u32, bits: u8) -> u32;
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation;
+    }
+
+    impl PhotometricInterpretationImpl for BlackIsZero {
+        fn encode_sample(&self, sample: u32, _bits: u8) -> u32 {
+            sample
+        }
+
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation {
+            ifd::tags::PhotometricInterpretation::BlackIsZero
+        }
+    }
+
+    impl PhotometricInterpretationImpl for WhiteIsZero {
+        fn encode_sample(&self, sample: u32, bits: u8) -> u32 {
+            (u32::MAX >> (32 - bits)) - (sample & (u32::MAX >> (32 - bits)))
+        }
+
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation {
+            ifd::tags::PhotometricInterpretation::WhiteIsZero
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::{BlackIsZero, GenericGrayscaleImageEncoder, GenericRGBImageEncoder, WhiteIsZero};
+    use crate::{
+        colors::{GenericGrayscale, GenericRGB, Samples},
+        compression::Compression,
+        decode::{DecodedImage, GenericImage},
+        encode::{
+            compression::{Lzw, NoCompression},
+            EncodeEndianness, ImageEncoder, Layout,
+        },
+        test_util::decode_single,
+        Image,
+    };
+
+    fn decode_generic(encoded: &[u8]) -> GenericImage {
+        let DecodedImage::GenericImage(image) = decode_single(encoded) else {
+            panic!("not a generic image")
+        };
+        image
+    }
+
+    fn encode_grayscale<E: EncodeEndianness, C: Compression<GenericGrayscale>>(
+        image: &Image<GenericGrayscale>,
+        compression: C,
+        bits: u8,
+    ) -> Vec<u8> {
+        GenericGrayscaleImageEncoder::<E, _, _>::new(image, compression, BlackIsZero, bits)
+            .with_layout(Layout::RowsPerStrip(2))
+            .encode()
+    }
+
+    #[test]
+    fn round_trip() {
+        // Bit depths that don't decode to a more specific image
+        for bits in [5u8, 12, 24, 32] {
+            let max = u32::MAX >> (32 - bits);
+            let samples: Vec<u32> = (0..7 * 5u32)
+                .map(|i| i.wrapping_mul(0x9E37_79B9) & max)
+                .collect();
+
+            let image = Image::new(samples.iter().map(|&s| Samples([s])).collect(), 7, 5);
+            for encoded in [
+                encode_grayscale::<LittleEndian, _>(&image, NoCompression, bits),
+                encode_grayscale::<BigEndian, _>(&image, Lzw, bits),
+            ] {
+                let decoded = decode_generic(&encoded);
+                assert_eq!(decoded.bits_per_sample(), [bits as u16]);
+                assert_eq!(decoded.samples(), samples);
+            }
+            let encoded = GenericGrayscaleImageEncoder::<LittleEndian, _, _>::new(
+                &image,
+                NoCompression,
+                WhiteIsZero,
+                bits,
+            )
+            .encode();
+            assert_eq!(decode_generic(&encoded).samples(), samples);
+
+            let image: Image<GenericRGB> = Image::new(
+                samples
+                    .iter()
+                    .map(|&s| Samples([s, max - s, s / 2]))
+                    .collect(),
+                7,
+                5,
+            );
+            for encoded in [
+                GenericRGBImageEncoder::<LittleEndian, _>::new(&image, NoCompression, bits)
+                    .encode(),
+                GenericRGBImageEncoder::<BigEndian, _>::new(&image, Lzw, bits).encode(),
+            ] {
+                let decoded = decode_generic(&encoded);
+                assert_eq!(decoded.bits_per_sample(), [bits as u16; 3]);
+                assert!(decoded
+                    .samples()
+                    .iter()
+                    .copied()
+                    .eq(image.iter_pixels().flatten().flat_map(|pixel| pixel.0)));
+            }
+        }
+    }
+
+    #[test]
+    fn whole_byte_samples_are_written_in_file_byte_order() {
+        let image = Image::new(vec![Samples([0x0102_0304])], 1, 1);
+        let encoded = encode_grayscale::<LittleEndian, _>(&image, NoCompression, 32);
+        assert_eq!(encoded[8..12], [4, 3, 2, 1]);
+        let encoded = encode_grayscale::<BigEndian, _>(&image, NoCompression, 32);
+        assert_eq!(encoded[8..12], [1, 2, 3, 4]);
+        let encoded = encode_grayscale::<LittleEndian, _>(&image, NoCompression, 24);
+        assert_eq!(encoded[8..11], [4, 3, 2]);
+
+        let image = Image::new(vec![Samples([0x0102_0304, 0x1, 0x0A0B_0C0D])], 1, 1);
+        let encoded =
+            GenericRGBImageEncoder::<LittleEndian, _>::new(&image, NoCompression, 24).encode();
+        assert_eq!(encoded[8..17], [4, 3, 2, 1, 0, 0, 0x0D, 0x0C, 0x0B]);
+
+        // Other samples are packed MSB-first
+        let image = Image::new(vec![Samples([0xABC]), Samples([0x123])], 2, 1);
+        let encoded = encode_grayscale::<LittleEndian, _>(&image, NoCompression, 12);
+        assert_eq!(encoded[8..11], [0xAB, 0xC1, 0x23]);
+    }
+}
@@ -0,0 +1,236 @@
+use std::marker::PhantomData;
+
+use crate::{
+    colors,
+    compression::Compression,
+    encode::{encode_ifds, private::EncodeResult},
+    ifd,
+    types::Short,
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::encode_image_data,
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout,
+};
+
+pub trait PhotometricInterpretation: private::PhotometricInterpretationImpl {}
+#[derive(Clone, Copy)]
+pub struct BlackIsZero;
+#[derive(Clone, Copy)]
+pub struct WhiteIsZero;
+
+impl PhotometricInterpretation for BlackIsZero {}
+impl PhotometricInterpretation for WhiteIsZero {}
+
+pub struct Grayscale16BitImageEncoder<'a, E, C, P = BlackIsZero>
+where
+    C: Compression<colors::Grayscale16Bit>,
+    P: PhotometricInterpretation,
+{
+    image: &'a Image<colors::Grayscale16Bit>,
+    image_compressor: C,
+    photo_interp: P,
+    layout: Layout,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E, C, P> Grayscale16BitImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::Grayscale16Bit>,
+    P: PhotometricInterpretation,
+{
+    pub fn new(image: &'a Image<colors::Grayscale16Bit>, compression: C, photo_interp: P) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            photo_interp,
+            layout: Layout::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+impl<'a, E, C, P> ImageEncoder for Grayscale16BitImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::Grayscale16Bit>,
+    P: PhotometricInterpretation,
+{
+}
+
+impl<'a, E, C, P> ImageEncoderImpl for Grayscale16BitImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::Grayscale16Bit>,
+    P: PhotometricInterpretation,
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_grayscale_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.photo_interp,
+            &self.image_compressor,
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(ifd::Tag::BitsPerSample, ifd::Values::Shorts(vec![16])),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![self.photo_interp.tag() as Short]),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+fn encode_grayscale_img<E, C, P>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &Image<colors::Grayscale16Bit>,
+    layout: Layout,
+    photo_iterp: P,
+    image_compressor: &C,
+) -> EncodeResult
+where
+    E: EncodeEndianness,
+    C: Compression<colors::Grayscale16Bit>,
+    P: PhotometricInterpretation,
+{
+    encode_image_data(wrt, image, layout, 16, image_compressor, |row| {
+        row.iter().flat_map(move |pixel| {
+            // Samples are written in the byte order of the file
+            let mut sample = [0; 2];
+            E::write_u16(&mut sample, photo_iterp.encode_pixel(*pixel));
+            sample
+        })
+    })
+}
+
+pub(crate) mod private {
+    use super::{BlackIsZero, WhiteIsZero};
+    use crate::{colors, ifd};
+
+    pub trait PhotometricInterpretationImpl: Copy {
+        fn encode_pixel(&self, pixel: colors::Grayscale16Bit) -> u16;
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation;
+    }
+
+    impl PhotometricInterpretationImpl for BlackIsZero {
+        fn encode_pixel(&self, pixel: colors::Grayscale16Bit) -> u16 {
+            pixel.0
+        }
+
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation {
+            ifd::tags::PhotometricInterpretation::BlackIsZero
+        }
+    }
+
+    impl PhotometricInterpretationImpl for WhiteIsZero {
+        fn encode_pixel(&self, pixel: colors::Grayscale16Bit) -> u16 {
+            0xFFFF - pixel.0
+        }
+
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation {
+            ifd::tags::PhotometricInterpretation::WhiteIsZero
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::{BlackIsZero, Grayscale16BitImageEncoder, WhiteIsZero};
+    use crate::{
+        colors::Grayscale16Bit,
+        encode::{
+            compression::{Lzw, NoCompression},
+            ImageEncoder,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    /// The image data of an uncompressed image with a single strip, which is
+    /// written right after the header.
+    fn strip_data(encoded: &[u8]) -> &[u8] {
+        &encoded[8..10]
+    }
+
+    #[test]
+    fn samples_are_written_in_file_byte_order() {
+        let image = Image::new(vec![Grayscale16Bit(0x1234)], 1, 1);
+        let encoded = Grayscale16BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            BlackIsZero,
+        )
+        .encode();
+        assert_eq!(strip_data(&encoded), [0x34, 0x12]);
+        let encoded =
+            Grayscale16BitImageEncoder::<BigEndian, _, _>::new(&image, NoCompression, BlackIsZero)
+                .encode();
+        assert_eq!(strip_data(&encoded), [0x12, 0x34]);
+        let encoded =
+            Grayscale16BitImageEncoder::<BigEndian, _, _>::new(&image, NoCompression, WhiteIsZero)
+                .encode();
+        assert_eq!(strip_data(&encoded), [0xED, 0xCB]);
+    }
+
+    #[test]
+    fn round_trip() {
+        // High and low bytes differ in every sample
+        let pixels = (0..5 * 3)
+            .map(|i| Grayscale16Bit(0x0102 + i * 0x1103))
+            .collect();
+        let image = Image::new(pixels, 5, 3);
+        for encoded in [
+            Grayscale16BitImageEncoder::<LittleEndian, _, _>::new(
+                &image,
+                NoCompression,
+                BlackIsZero,
+            )
+            .encode(),
+            Grayscale16BitImageEncoder::<BigEndian, _, _>::new(&image, NoCompression, BlackIsZero)
+                .encode(),
+            Grayscale16BitImageEncoder::<LittleEndian, _, _>::new(&image, Lzw, WhiteIsZero)
+                .encode(),
+            Grayscale16BitImageEncoder::<BigEndian, _, _>::new(&image, Lzw, WhiteIsZero).encode(),
+        ] {
+            assert_decodes_to(&encoded, &image);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::marker::PhantomData;
+
+use crate::{
+    colors,
+    compression::Compression,
+    encode::{encode_ifds, private::EncodeResult},
+    ifd,
+    types::Short,
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::{encode_image_data, encode_planar_image_data},
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout, PlanarConfiguration,
+};
+
+pub trait PhotometricInterpretation: private::PhotometricInterpretationImpl {}
+#[derive(Clone, Copy)]
+pub struct BlackIsZero;
+#[derive(Clone, Copy)]
+pub struct WhiteIsZero;
+
+impl PhotometricInterpretation for BlackIsZero {}
+impl PhotometricInterpretation for WhiteIsZero {}
+
+pub struct GrayscaleAlphaImageEncoder<'a, E, C, P = BlackIsZero>
+where
+    C: Compression<colors::GrayscaleAlpha>,
+    P: PhotometricInterpretation,
+{
+    image: &'a Image<colors::GrayscaleAlpha>,
+    image_compressor: C,
+    alpha: colors::Alpha,
+    photo_interp: P,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E, C, P> GrayscaleAlphaImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GrayscaleAlpha>,
+    P: PhotometricInterpretation,
+{
+    /// `alpha` is whether the grays of `image` have been premultiplied by
+    /// their alpha.
+    pub fn new(
+        image: &'a Image<colors::GrayscaleAlpha>,
+        compression: C,
+        alpha: colors::Alpha,
+        photo_interp: P,
+    ) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            alpha,
+            photo_interp,
+            layout: Layout::default(),
+            planar_configuration: PlanarConfiguration::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets whether the samples of each pixel are stored together or in
+    /// separate planes.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
+}
+
+impl<'a, E, C, P> ImageEncoder for GrayscaleAlphaImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GrayscaleAlpha>,
+    P: PhotometricInterpretation,
+{
+}
+
+impl<'a, E, C, P> ImageEncoderImpl for GrayscaleAlphaImageEncoder<'a, E, C, P>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GrayscaleAlpha>,
+    P: PhotometricInterpretation,
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_grayscale_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.planar_configuration,
+            self.photo_interp,
+            &self.image_compressor,
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(ifd::Tag::BitsPerSample, ifd::Values::Shorts(vec![8, 8])),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![self.photo_interp.tag() as Short]),
+            ),
+            ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![2])),
+            ifd::Entry::new(
+                ifd::Tag::PlanarConfiguration,
+                ifd::Values::Shorts(vec![self.planar_configuration as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ExtraSamples,
+                ifd::Values::Shorts(vec![ifd::tags::ExtraSamples::from(self.alpha) as Short]),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+fn encode_grayscale_img<E, C, P>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &Image<colors::GrayscaleAlpha>,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    photo_iterp: P,
+    image_compressor: &C,
+) -> EncodeResult
+where
+    E: EncodeEndianness,
+    C: Compression<colors::GrayscaleAlpha>,
+    P: PhotometricInterpretation,
+{
+    match planar_configuration {
+        PlanarConfiguration::Chunky => {
+            encode_image_data(wrt, image, layout, 16, image_compressor, |row| {
+                row.iter()
+                    .flat_map(move |pixel| [photo_iterp.encode_pixel(*pixel), pixel.alpha])
+            })
+        }
+        PlanarConfiguration::Planar => encode_planar_image_data(
+            wrt,
+            image,
+            layout,
+            &[8, 8],
+            image_compressor,
+            |row, plane| {
+                row.iter()
+                    .map(move |pixel| [photo_iterp.encode_pixel(*pixel), pixel.alpha][plane])
+            },
+        ),
+    }
+}
+
+pub(crate) mod private {
+    use super::{BlackIsZero, WhiteIsZero};
+    use crate::{colors, ifd};
+
+    pub trait PhotometricInterpretationImpl: Copy {
+        fn encode_pixel(&self, pixel: colors::GrayscaleAlpha) -> u8;
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation;
+    }
+
+    impl PhotometricInterpretationImpl for BlackIsZero {
+        fn encode_pixel(&self, pixel: colors::GrayscaleAlpha) -> u8 {
+            pixel.gray
+        }
+
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation {
+            ifd::tags::PhotometricInterpretation::BlackIsZero
+        }
+    }
+
+    impl PhotometricInterpretationImpl for WhiteIsZero {
+        fn encode_pixel(&self, pixel: colors::GrayscaleAlpha) -> u8 {
+            0xFF - pixel.gray
+        }
+
+        fn tag(&self) -> ifd::tags::PhotometricInterpretation {
+            ifd::tags::PhotometricInterpretation::WhiteIsZero
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::{BlackIsZero, GrayscaleAlphaImageEncoder, WhiteIsZero};
+    use crate::{
+        colors::{Alpha, GrayscaleAlpha},
+        encode::{
+            compression::{Lzw, NoCompression},
+            ImageEncoder, Layout, PlanarConfiguration,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    #[test]
+    fn round_trip() {
+        let pixels = (0..7 * 5)
+            .map(|i| GrayscaleAlpha::new(i * 7, 255 - i))
+            .collect();
+        let image = Image::new(pixels, 7, 5);
+        for planar_configuration in [PlanarConfiguration::Chunky, PlanarConfiguration::Planar] {
+            for encoded in [
+                GrayscaleAlphaImageEncoder::<LittleEndian, _, _>::new(
+                    &image,
+                    NoCompression,
+                    Alpha::Associated,
+                    BlackIsZero,
+                )
+                .with_planar_configuration(planar_configuration)
+                .encode(),
+                GrayscaleAlphaImageEncoder::<BigEndian, _, _>::new(
+                    &image,
+                    Lzw,
+                    Alpha::Unassociated,
+                    WhiteIsZero,
+                )
+                .with_planar_configuration(planar_configuration)
+                .with_layout(Layout::RowsPerStrip(2))
+                .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
+}
@@ -1,10 +1,21 @@
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
 
+use crate::ifd;
+
 pub trait EncodeEndianness: ByteOrder + private::EndiannessSentinel {}
 impl EncodeEndianness for LittleEndian {}
 impl EncodeEndianness for BigEndian {}
 
-pub(crate) const LEN: usize = 8;
+/// Returns the length of the header
+pub(crate) const fn len(format: ifd::Format) -> usize {
+    match format {
+        // Byte order, magic number and first IFD offset
+        ifd::Format::Classic => 2 + 2 + 4,
+        // Byte order, magic number, offset byte size, reserved and first IFD
+        // offset
+        ifd::Format::BigTiff => 2 + 2 + 2 + 2 + 8,
+    }
+}
 
 mod private {
     use byteorder::{BigEndian, ByteOrder, LittleEndian};
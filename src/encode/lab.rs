@@ -0,0 +1,209 @@
+use std::marker::PhantomData;
+
+use crate::{
+    colors,
+    compression::Compression,
+    encode::{encode_ifds, private::EncodeResult},
+    ifd,
+    types::Short,
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::{encode_image_data, encode_planar_image_data},
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout, PlanarConfiguration,
+};
+
+pub struct LabImageEncoder<'a, E, C>
+where
+    C: Compression<colors::Lab>,
+{
+    image: &'a Image<colors::Lab>,
+    image_compressor: C,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    encoding: colors::LabEncoding,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::Lab>> LabImageEncoder<'a, E, C> {
+    pub fn new(image: &'a Image<colors::Lab>, compression: C) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            layout: Layout::default(),
+            planar_configuration: PlanarConfiguration::default(),
+            encoding: colors::LabEncoding::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets whether the samples of each pixel are stored together or in
+    /// separate planes.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
+
+    /// Sets how the L*, a* and b* samples are stored.
+    pub fn with_encoding(mut self, encoding: colors::LabEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::Lab>> ImageEncoder
+    for LabImageEncoder<'a, E, C>
+{
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::Lab>> ImageEncoderImpl
+    for LabImageEncoder<'a, E, C>
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_lab_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.planar_configuration,
+            self.encoding,
+            &self.image_compressor,
+        );
+        let photo_interp = match self.encoding {
+            colors::LabEncoding::Cie => ifd::tags::PhotometricInterpretation::CIELab,
+            colors::LabEncoding::Icc => ifd::tags::PhotometricInterpretation::ICCLab,
+            colors::LabEncoding::Itu => ifd::tags::PhotometricInterpretation::ITULab,
+        };
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(ifd::Tag::BitsPerSample, ifd::Values::Shorts(vec![8, 8, 8])),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![photo_interp as Short]),
+            ),
+            ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![3])),
+            ifd::Entry::new(
+                ifd::Tag::PlanarConfiguration,
+                ifd::Values::Shorts(vec![self.planar_configuration as Short]),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+fn encode_lab_img<C: Compression<colors::Lab>, E: EncodeEndianness>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &Image<colors::Lab>,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    encoding: colors::LabEncoding,
+    image_compressor: &C,
+) -> EncodeResult {
+    match planar_configuration {
+        PlanarConfiguration::Chunky => {
+            encode_image_data(wrt, image, layout, 24, image_compressor, |row| {
+                row.iter()
+                    .flat_map(move |pixel| encoding.encode_samples(*pixel))
+            })
+        }
+        PlanarConfiguration::Planar => encode_planar_image_data(
+            wrt,
+            image,
+            layout,
+            &[8, 8, 8],
+            image_compressor,
+            |row, plane| {
+                row.iter()
+                    .map(move |pixel| encoding.encode_samples(*pixel)[plane])
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::LabImageEncoder;
+    use crate::{
+        colors::{Lab, LabEncoding},
+        encode::{
+            compression::{Lzw, NoCompression},
+            ImageEncoder, Layout, PlanarConfiguration,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    #[test]
+    fn round_trip() {
+        for encoding in [LabEncoding::Cie, LabEncoding::Icc, LabEncoding::Itu] {
+            // Colors that each encoding can store exactly
+            let pixels = (0..7 * 5u8)
+                .map(|i| encoding.decode_samples([i * 7, 255 - i * 3, i * 5 + 40]))
+                .collect();
+            let image = Image::new(pixels, 7, 5);
+            for planar_configuration in [PlanarConfiguration::Chunky, PlanarConfiguration::Planar] {
+                for encoded in [
+                    LabImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                        .with_encoding(encoding)
+                        .with_planar_configuration(planar_configuration)
+                        .encode(),
+                    LabImageEncoder::<BigEndian, _>::new(&image, Lzw)
+                        .with_encoding(encoding)
+                        .with_planar_configuration(planar_configuration)
+                        .with_layout(Layout::RowsPerStrip(2))
+                        .encode(),
+                ] {
+                    assert_decodes_to(&encoded, &image);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn white_is_stored_per_encoding() {
+        let image = Image::new(vec![Lab::new(100.0, 0.0, 0.0)], 1, 1);
+        for (encoding, samples) in [
+            (LabEncoding::Cie, [255, 0, 0]),
+            (LabEncoding::Icc, [255, 128, 128]),
+            (LabEncoding::Itu, [255, 128, 96]),
+        ] {
+            let encoded = LabImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                .with_encoding(encoding)
+                .encode();
+            assert_eq!(encoded[8..11], samples);
+        }
+    }
+}
@@ -0,0 +1,587 @@
+use crate::{
+    colors::Color,
+    encode::{compression::sealed::CompressionImpl, private::EncodeResult},
+    ifd,
+    types::Byte,
+    Image,
+};
+
+use super::{buffer::TiffEncodeBuffer, EncodeEndianness};
+
+/// How the image data of an encoded image is split up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// The whole image is stored in a single strip.
+    #[default]
+    SingleStrip,
+    /// Each strip holds the given number of rows, except possibly the last.
+    RowsPerStrip(u32),
+    /// Each strip holds as many rows as fit in the given number of bytes
+    /// before compression, with at least one row per strip. The spec
+    /// recommends strips of about 8K bytes.
+    StripBytes(usize),
+    /// The image is split into tiles of the given size. Tiles past the right
+    /// and bottom edges of the image are padded.
+    Tiles(TileSize),
+}
+
+impl Layout {
+    /// Strips of about 8K bytes, as recommended by the spec.
+    pub const RECOMMENDED_STRIPS: Self = Self::StripBytes(8 * 1024);
+
+    /// Returns how many rows of the image data are in each strip, where each
+    /// row of the data covers `rows_per_data_row` rows of the image.
+    fn rows_per_strip(
+        &self,
+        height: usize,
+        bytes_per_row: usize,
+        rows_per_data_row: usize,
+    ) -> usize {
+        let rows = match *self {
+            Layout::SingleStrip | Layout::Tiles(_) => height,
+            Layout::RowsPerStrip(rows) => (rows as usize).div_ceil(rows_per_data_row),
+            Layout::StripBytes(bytes) => bytes / bytes_per_row.max(1),
+        };
+        rows.clamp(1, height.max(1))
+    }
+}
+
+/// The width and length of each tile in a tiled image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileSize {
+    width: u32,
+    length: u32,
+}
+
+impl TileSize {
+    /// # Panics
+    ///
+    /// Panics if the width or length is 0 or not a multiple of 16
+    pub fn new(width: u32, length: u32) -> Self {
+        Self::new_checked(width, length)
+            .expect("tile width and length must be non-zero multiples of 16")
+    }
+
+    pub fn new_checked(width: u32, length: u32) -> Option<Self> {
+        if width == 0 || length == 0 || !width.is_multiple_of(16) || !length.is_multiple_of(16) {
+            None
+        } else {
+            Some(Self { width, length })
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+}
+
+/// Compresses and writes the image data as strips or tiles.
+///
+/// `encode_row` must convert a row of pixels, or the part of a row in a tile,
+/// to bytes with the final byte padded if necessary.
+pub(crate) fn encode_image_data<'a, T, E, C, F, I>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &'a Image<T>,
+    layout: Layout,
+    bits_per_pixel: usize,
+    image_compressor: &C,
+    encode_row: F,
+) -> EncodeResult
+where
+    T: Color,
+    E: EncodeEndianness,
+    C: CompressionImpl,
+    F: Fn(&'a [T]) -> I,
+    I: Iterator<Item = Byte>,
+{
+    let rows = image.rows().collect::<Vec<_>>();
+    encode_subsampled_image_data(
+        wrt,
+        &rows,
+        (1, 1),
+        layout,
+        bits_per_pixel,
+        image_compressor,
+        encode_row,
+    )
+}
+
+/// Compresses and writes image data where each data unit covers a
+/// `(horizontal, vertical)` block of pixels, such as subsampled YCbCr data.
+///
+/// `rows` are the rows of data units, and `encode_row` must convert a row of
+/// units, or the part of a row in a tile, to bytes.
+pub(crate) fn encode_subsampled_image_data<'a, T, E, C, F, I>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    rows: &[&'a [T]],
+    subsampling: (usize, usize),
+    layout: Layout,
+    bits_per_unit: usize,
+    image_compressor: &C,
+    encode_row: F,
+) -> EncodeResult
+where
+    E: EncodeEndianness,
+    C: CompressionImpl,
+    F: Fn(&'a [T]) -> I,
+    I: Iterator<Item = Byte>,
+{
+    match layout {
+        Layout::Tiles(tile_size) => encode_tiles(
+            wrt,
+            rows,
+            subsampling,
+            tile_size,
+            bits_per_unit,
+            image_compressor,
+            encode_row,
+        ),
+        _ => encode_strips(
+            wrt,
+            rows,
+            subsampling.1,
+            layout,
+            bits_per_unit,
+            image_compressor,
+            encode_row,
+        ),
+    }
+}
+
+/// Compresses and writes the image data with each sample stored in a separate
+/// plane, one plane after another.
+///
+/// `encode_plane_row` must convert a row of pixels, or the part of a row in a
+/// tile, to the bytes of the samples in the given plane.
+pub(crate) fn encode_planar_image_data<'a, T, E, C, F, I>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &'a Image<T>,
+    layout: Layout,
+    bits_per_sample: &[usize],
+    image_compressor: &C,
+    encode_plane_row: F,
+) -> EncodeResult
+where
+    T: Color,
+    E: EncodeEndianness,
+    C: CompressionImpl,
+    F: Fn(&'a [T], usize) -> I,
+    I: Iterator<Item = Byte>,
+{
+    bits_per_sample
+        .iter()
+        .enumerate()
+        .map(|(plane, &bits)| {
+            encode_image_data(wrt, image, layout, bits, image_compressor, |row| {
+                encode_plane_row(row, plane)
+            })
+        })
+        .reduce(EncodeResult::append)
+        .expect("image must have at least one plane")
+}
+
+fn encode_strips<'a, T, E, C, F, I>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    rows: &[&'a [T]],
+    rows_per_unit: usize,
+    layout: Layout,
+    bits_per_unit: usize,
+    image_compressor: &C,
+    encode_row: F,
+) -> EncodeResult
+where
+    E: EncodeEndianness,
+    C: CompressionImpl,
+    F: Fn(&'a [T]) -> I,
+    I: Iterator<Item = Byte>,
+{
+    let width = rows.first().map_or(0, |row| row.len());
+    let bytes_per_row = (width * bits_per_unit).div_ceil(8);
+    let rows_per_strip = layout.rows_per_strip(rows.len(), bytes_per_row, rows_per_unit);
+
+    let mut image_strip_offsets = Vec::new();
+    let mut image_strip_bytecounts = Vec::new();
+    for strip in rows.chunks(rows_per_strip) {
+        let strip_inx = wrt.align_and_get_len();
+        image_compressor.encode(wrt, strip.iter().flat_map(|row| encode_row(row)));
+        image_strip_offsets.push(strip_inx as u64);
+        image_strip_bytecounts.push((wrt.len() - strip_inx) as u64);
+    }
+
+    EncodeResult::Strips {
+        image_strip_offsets,
+        image_strip_bytecounts,
+        rows_per_strip: (rows_per_strip * rows_per_unit) as u64,
+    }
+}
+
+fn encode_tiles<'a, T, E, C, F, I>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    rows: &[&'a [T]],
+    (horizontal, vertical): (usize, usize),
+    tile_size: TileSize,
+    bits_per_unit: usize,
+    image_compressor: &C,
+    encode_row: F,
+) -> EncodeResult
+where
+    E: EncodeEndianness,
+    C: CompressionImpl,
+    F: Fn(&'a [T]) -> I,
+    I: Iterator<Item = Byte>,
+{
+    // Tile sizes are multiples of 16, so they always hold whole units
+    let tile_width = tile_size.width as usize / horizontal;
+    let tile_length = tile_size.length as usize / vertical;
+    // Tile widths are multiples of 16, so tile rows always end on a byte
+    let bytes_per_tile_row = tile_width * bits_per_unit / 8;
+
+    let width = rows.first().map_or(0, |row| row.len());
+    let mut image_tile_offsets = Vec::new();
+    let mut image_tile_bytecounts = Vec::new();
+    for tile_rows in rows.chunks(tile_length) {
+        for tile_start in (0..width).step_by(tile_width) {
+            let tile_end = width.min(tile_start + tile_width);
+            let mut tile = Vec::with_capacity(bytes_per_tile_row * tile_length);
+            for row in tile_rows {
+                let tile_row_start = tile.len();
+                tile.extend(encode_row(&row[tile_start..tile_end]));
+                // Pad tiles past the right edge of the image
+                tile.resize(tile_row_start + bytes_per_tile_row, 0);
+            }
+            // Pad tiles past the bottom edge of the image
+            tile.resize(bytes_per_tile_row * tile_length, 0);
+
+            let tile_inx = wrt.align_and_get_len();
+            image_compressor.encode(wrt, tile.into_iter());
+            image_tile_offsets.push(tile_inx as u64);
+            image_tile_bytecounts.push((wrt.len() - tile_inx) as u64);
+        }
+    }
+
+    EncodeResult::Tiles {
+        tile_size,
+        image_tile_offsets,
+        image_tile_bytecounts,
+    }
+}
+
+impl EncodeResult {
+    /// Appends the strips or tiles of the next plane.
+    fn append(self, next: Self) -> Self {
+        match (self, next) {
+            (
+                EncodeResult::Strips {
+                    mut image_strip_offsets,
+                    mut image_strip_bytecounts,
+                    rows_per_strip,
+                },
+                EncodeResult::Strips {
+                    image_strip_offsets: next_offsets,
+                    image_strip_bytecounts: next_bytecounts,
+                    ..
+                },
+            ) => {
+                image_strip_offsets.extend(next_offsets);
+                image_strip_bytecounts.extend(next_bytecounts);
+                EncodeResult::Strips {
+                    image_strip_offsets,
+                    image_strip_bytecounts,
+                    rows_per_strip,
+                }
+            }
+            (
+                EncodeResult::Tiles {
+                    tile_size,
+                    mut image_tile_offsets,
+                    mut image_tile_bytecounts,
+                },
+                EncodeResult::Tiles {
+                    image_tile_offsets: next_offsets,
+                    image_tile_bytecounts: next_bytecounts,
+                    ..
+                },
+            ) => {
+                image_tile_offsets.extend(next_offsets);
+                image_tile_bytecounts.extend(next_bytecounts);
+                EncodeResult::Tiles {
+                    tile_size,
+                    image_tile_offsets,
+                    image_tile_bytecounts,
+                }
+            }
+            _ => unreachable!("planes are all encoded with the same layout"),
+        }
+    }
+
+    /// Returns the entries describing where the image data was written.
+    pub(crate) fn ifd_entries(self) -> Vec<ifd::Entry> {
+        match self {
+            EncodeResult::Strips {
+                image_strip_offsets,
+                image_strip_bytecounts,
+                rows_per_strip,
+            } => vec![
+                ifd::Entry::new(
+                    ifd::Tag::StripOffsets,
+                    ifd::Values::unsigned(image_strip_offsets),
+                ),
+                ifd::Entry::new(
+                    ifd::Tag::RowsPerStrip,
+                    ifd::Values::unsigned(vec![rows_per_strip]),
+                ),
+                ifd::Entry::new(
+                    ifd::Tag::StripByteCounts,
+                    ifd::Values::unsigned(image_strip_bytecounts),
+                ),
+            ],
+            EncodeResult::Tiles {
+                tile_size,
+                image_tile_offsets,
+                image_tile_bytecounts,
+            } => vec![
+                ifd::Entry::new(
+                    ifd::Tag::TileWidth,
+                    ifd::Values::unsigned(vec![tile_size.width as u64]),
+                ),
+                ifd::Entry::new(
+                    ifd::Tag::TileLength,
+                    ifd::Values::unsigned(vec![tile_size.length as u64]),
+                ),
+                ifd::Entry::new(
+                    ifd::Tag::TileOffsets,
+                    ifd::Values::unsigned(image_tile_offsets),
+                ),
+                ifd::Entry::new(
+                    ifd::Tag::TileByteCounts,
+                    ifd::Values::unsigned(image_tile_bytecounts),
+                ),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::{Layout, TileSize};
+    use crate::{
+        colors::{Grayscale4Bit, Grayscale8Bit, RGB},
+        encode::{
+            compression::{Lzw, NoCompression, PackBits},
+            grayscale_4bit::{self, Grayscale4BitImageEncoder},
+            grayscale_8bit::{BlackIsZero, Grayscale8BitImageEncoder},
+            rgb::RGBImageEncoder,
+            ImageEncoder, PlanarConfiguration,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    fn grayscale_8bit_image(width: usize, height: usize) -> Image<Grayscale8Bit> {
+        let pixels = (0..width * height)
+            .map(|i| Grayscale8Bit((i * 7 % 256) as u8))
+            .collect();
+        Image::new(pixels, width, height)
+    }
+
+    /// Reads the number of StripOffsets from a little endian file.
+    fn strip_count(encoded: &[u8]) -> u32 {
+        let read_u16 = |inx: usize| u16::from_le_bytes([encoded[inx], encoded[inx + 1]]);
+        let ifd_inx = u32::from_le_bytes(encoded[4..8].try_into().unwrap()) as usize;
+        (0..read_u16(ifd_inx) as usize)
+            .map(|entry| ifd_inx + 2 + entry * 12)
+            .find(|&inx| read_u16(inx) == 273)
+            .map(|inx| u32::from_le_bytes(encoded[inx + 4..inx + 8].try_into().unwrap()))
+            .expect("no StripOffsets")
+    }
+
+    #[test]
+    fn rows_per_strip() {
+        assert_eq!(Layout::SingleStrip.rows_per_strip(10, 7, 1), 10);
+        assert_eq!(Layout::RowsPerStrip(3).rows_per_strip(10, 7, 1), 3);
+        assert_eq!(Layout::RowsPerStrip(0).rows_per_strip(10, 7, 1), 1);
+        assert_eq!(Layout::RowsPerStrip(100).rows_per_strip(10, 7, 1), 10);
+        assert_eq!(Layout::StripBytes(21).rows_per_strip(10, 7, 1), 3);
+        assert_eq!(Layout::StripBytes(27).rows_per_strip(10, 7, 1), 3);
+        // At least one row, however long rows are
+        assert_eq!(Layout::StripBytes(5).rows_per_strip(10, 7, 1), 1);
+        assert_eq!(Layout::RECOMMENDED_STRIPS.rows_per_strip(1000, 100, 1), 81);
+        assert_eq!(Layout::RECOMMENDED_STRIPS.rows_per_strip(10, 100, 1), 10);
+        // Each data row of a vertically subsampled image covers 2 image rows
+        assert_eq!(Layout::RowsPerStrip(3).rows_per_strip(5, 7, 2), 2);
+        assert_eq!(Layout::RowsPerStrip(4).rows_per_strip(5, 7, 2), 2);
+    }
+
+    #[test]
+    fn strip_layouts() {
+        let image = grayscale_8bit_image(7, 12);
+        for (layout, strips) in [
+            (Layout::SingleStrip, 1),
+            (Layout::RowsPerStrip(3), 4),
+            (Layout::RowsPerStrip(12), 1),
+            (Layout::StripBytes(28), 3),
+            (Layout::StripBytes(1), 12),
+            (Layout::RECOMMENDED_STRIPS, 1),
+        ] {
+            let encoded = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+                &image,
+                NoCompression,
+                BlackIsZero,
+            )
+            .with_layout(layout)
+            .encode();
+            assert_eq!(strip_count(&encoded), strips, "{layout:?}");
+            assert_decodes_to(&encoded, &image);
+            for encoded in [
+                Grayscale8BitImageEncoder::<BigEndian, _, _>::new(&image, PackBits, BlackIsZero)
+                    .with_layout(layout)
+                    .encode(),
+                Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(&image, Lzw, BlackIsZero)
+                    .with_layout(layout)
+                    .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
+
+    #[test]
+    fn recommended_strips_are_about_8k() {
+        let image = grayscale_8bit_image(1000, 32);
+        let encoded = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            BlackIsZero,
+        )
+        .with_layout(Layout::RECOMMENDED_STRIPS)
+        .encode();
+        // 8 rows of 1000 bytes fit in each strip
+        assert_eq!(strip_count(&encoded), 4);
+        assert_decodes_to(&encoded, &image);
+    }
+
+    #[test]
+    fn short_final_strip() {
+        let image = grayscale_8bit_image(7, 10);
+        // 3 rows per strip leaves 1 row for the last strip
+        for layout in [
+            Layout::RowsPerStrip(3),
+            Layout::RowsPerStrip(4),
+            Layout::StripBytes(21),
+        ] {
+            for encoded in [
+                Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+                    &image,
+                    NoCompression,
+                    BlackIsZero,
+                )
+                .with_layout(layout)
+                .encode(),
+                Grayscale8BitImageEncoder::<BigEndian, _, _>::new(&image, PackBits, BlackIsZero)
+                    .with_layout(layout)
+                    .encode(),
+                Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(&image, Lzw, BlackIsZero)
+                    .with_layout(layout)
+                    .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
+
+    #[test]
+    fn short_final_strip_of_half_bytes() {
+        // Each row of 5 pixels ends in half a byte of padding
+        let pixels = (0..35).map(|i| Grayscale4Bit::new(i % 16)).collect();
+        let image = Image::new(pixels, 5, 7);
+        let encoded = Grayscale4BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            grayscale_4bit::BlackIsZero,
+        )
+        .with_layout(Layout::RowsPerStrip(2))
+        .encode();
+        assert_eq!(strip_count(&encoded), 4);
+        assert_decodes_to(&encoded, &image);
+    }
+
+    #[test]
+    fn padded_tiles() {
+        // 17x33 leaves a single column and row of pixels in the last tiles
+        let image = grayscale_8bit_image(17, 33);
+        let layout = Layout::Tiles(TileSize::new(16, 16));
+        for encoded in [
+            Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+                &image,
+                NoCompression,
+                BlackIsZero,
+            )
+            .with_layout(layout)
+            .encode(),
+            Grayscale8BitImageEncoder::<BigEndian, _, _>::new(&image, PackBits, BlackIsZero)
+                .with_layout(layout)
+                .encode(),
+            Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(&image, Lzw, BlackIsZero)
+                .with_layout(layout)
+                .encode(),
+        ] {
+            assert_decodes_to(&encoded, &image);
+        }
+    }
+
+    #[test]
+    fn padded_tiles_of_half_bytes() {
+        let pixels = (0..21 * 5).map(|i| Grayscale4Bit::new(i % 16)).collect();
+        let image = Image::new(pixels, 21, 5);
+        let encoded = Grayscale4BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            PackBits,
+            grayscale_4bit::BlackIsZero,
+        )
+        .with_layout(Layout::Tiles(TileSize::new(16, 16)))
+        .encode();
+        assert_decodes_to(&encoded, &image);
+    }
+
+    #[test]
+    fn padded_planar_tiles() {
+        let pixels = (0..40 * 20)
+            .map(|i| RGB {
+                r: i as u8,
+                g: (i / 3) as u8,
+                b: 255 - i as u8,
+            })
+            .collect();
+        let image = Image::new(pixels, 40, 20);
+        for planar_configuration in [PlanarConfiguration::Chunky, PlanarConfiguration::Planar] {
+            let encoded = RGBImageEncoder::<LittleEndian, _>::new(&image, Lzw)
+                .with_layout(Layout::Tiles(TileSize::new(32, 16)))
+                .with_planar_configuration(planar_configuration)
+                .encode();
+            assert_decodes_to(&encoded, &image);
+        }
+    }
+
+    #[test]
+    fn image_smaller_than_one_tile() {
+        let image = grayscale_8bit_image(3, 5);
+        let encoded =
+            Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(&image, PackBits, BlackIsZero)
+                .with_layout(Layout::Tiles(TileSize::new(32, 16)))
+                .encode();
+        assert_decodes_to(&encoded, &image);
+    }
+
+    #[test]
+    fn tile_sizes_must_be_multiples_of_16() {
+        assert!(TileSize::new_checked(16, 48).is_some());
+        assert!(TileSize::new_checked(24, 16).is_none());
+        assert!(TileSize::new_checked(16, 0).is_none());
+    }
+}
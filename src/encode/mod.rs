@@ -1,37 +1,72 @@
 pub mod bilevel;
 mod buffer;
+pub mod cmyk;
 pub mod compression;
+pub mod generic;
+pub mod grayscale_16bit;
 pub mod grayscale_4bit;
 pub mod grayscale_8bit;
+pub mod grayscale_alpha;
 mod image_header;
+pub mod lab;
+pub mod layout;
 pub mod palette_color;
 pub mod rgb;
+pub mod rgb16;
+pub mod rgba;
+pub mod sample;
+pub mod ycbcr;
 
+pub use crate::ifd::tags::PlanarConfiguration;
 pub use bilevel::BilevelImageEncoder;
 pub use image_header::EncodeEndianness;
+pub use layout::{Layout, TileSize};
 
-use crate::ifd;
+use crate::{ifd, metadata::Metadata, resolution::Resolution};
 
 use self::buffer::TiffEncodeBuffer;
 
 /// Encodes multiple images into a single file.
 ///
 /// # Panics
+/// Panics if the iterator has no elements, or if the file would be over
+/// 4 GiB. Use [`encode_bigtiff_images`] for larger files.
+pub fn encode_images<'a, Endianness, E, I>(images: I) -> Vec<u8>
+where
+    Endianness: EncodeEndianness + 'static,
+    E: ImageEncoder<Endianness = Endianness> + ?Sized + 'a,
+    I: Iterator<Item = &'a E>,
+{
+    encode_images_in_format(images, ifd::Format::Classic)
+}
+
+/// Encodes multiple images into a single BigTIFF file.
+///
+/// # Panics
 /// Panics if the iterator has no elements.
-pub fn encode_images<'a, Endianness, E, I>(mut images: I) -> Vec<u8>
+pub fn encode_bigtiff_images<'a, Endianness, E, I>(images: I) -> Vec<u8>
+where
+    Endianness: EncodeEndianness + 'static,
+    E: ImageEncoder<Endianness = Endianness> + ?Sized + 'a,
+    I: Iterator<Item = &'a E>,
+{
+    encode_images_in_format(images, ifd::Format::BigTiff)
+}
+
+fn encode_images_in_format<'a, Endianness, E, I>(mut images: I, format: ifd::Format) -> Vec<u8>
 where
     Endianness: EncodeEndianness + 'static,
     E: ImageEncoder<Endianness = Endianness> + ?Sized + 'a,
     I: Iterator<Item = &'a E>,
 {
-    let mut encoded = TiffEncodeBuffer::<Endianness>::new();
+    let mut encoded = TiffEncodeBuffer::<Endianness>::new(format);
 
     let mut prev_ifd_info = match images.next() {
         Some(first) => {
             let ifd_info = first.append_image_to_buffer(&mut encoded);
             encoded
                 .get_tiff_header()
-                .set_first_ifd_offset(ifd_info.inx.try_into().unwrap());
+                .set_first_ifd_offset(ifd_info.inx as u64);
             ifd_info
         }
         None => panic!("tiff file must have at least one image"),
@@ -40,7 +75,7 @@ where
         let ifd_info = image.append_image_to_buffer(&mut encoded);
         encoded
             .get_ifd_at(prev_ifd_info.inx, prev_ifd_info.entry_count)
-            .set_next_ifd_offset(ifd_info.inx.try_into().unwrap());
+            .set_next_ifd_offset(ifd_info.inx as u64);
         prev_ifd_info = ifd_info;
     }
 
@@ -49,50 +84,163 @@ where
 
 pub trait ImageEncoder: private::ImageEncoderImpl {
     /// Encodes image into a file with that single image.
+    ///
+    /// # Panics
+    /// Panics if the file would be over 4 GiB. Use
+    /// [`ImageEncoder::encode_bigtiff`] for larger images.
     fn encode(&self) -> Vec<u8> {
-        let mut encoded = TiffEncodeBuffer::<Self::Endianness>::new();
+        encode_in_format(self, ifd::Format::Classic)
+    }
 
-        let ifd_inx = self
-            .append_image_to_buffer(&mut encoded)
-            .inx
-            .try_into()
-            .unwrap();
-        // Update header to point to the correct IDF offset
-        encoded.get_tiff_header().set_first_ifd_offset(ifd_inx);
+    /// Encodes image into a BigTIFF file with that single image.
+    ///
+    /// BigTIFF files use 64-bit offsets, so they can be larger than 4 GiB.
+    fn encode_bigtiff(&self) -> Vec<u8> {
+        encode_in_format(self, ifd::Format::BigTiff)
+    }
 
-        encoded.to_bytes()
+    /// Also writes the given entries, such as the private tags of a decoded
+    /// image. Entries with a tag the encoder writes itself are ignored.
+    fn with_raw_entries(self, raw_entries: Vec<ifd::RawEntry>) -> WithEntries<Self>
+    where
+        Self: Sized,
+    {
+        WithEntries {
+            encoder: self,
+            entries: raw_entries,
+        }
     }
+
+    /// Sets the physical resolution of the image, which is otherwise one
+    /// pixel per inch.
+    fn with_resolution(self, resolution: Resolution) -> WithEntries<Self>
+    where
+        Self: Sized,
+    {
+        WithEntries {
+            encoder: self,
+            entries: resolution
+                .ifd_entries()
+                .into_iter()
+                .map(ifd::RawEntry::from)
+                .collect(),
+        }
+    }
+
+    /// Also writes the fields of the metadata that are set.
+    fn with_metadata(self, metadata: &Metadata) -> WithEntries<Self>
+    where
+        Self: Sized,
+    {
+        WithEntries {
+            encoder: self,
+            entries: metadata
+                .ifd_entries()
+                .into_iter()
+                .map(ifd::RawEntry::from)
+                .collect(),
+        }
+    }
+}
+
+fn encode_in_format<E: ImageEncoder + ?Sized>(image: &E, format: ifd::Format) -> Vec<u8> {
+    let mut encoded = TiffEncodeBuffer::<E::Endianness>::new(format);
+
+    let ifd_inx = image.append_image_to_buffer(&mut encoded).inx as u64;
+    // Update header to point to the correct IDF offset
+    encoded.get_tiff_header().set_first_ifd_offset(ifd_inx);
+
+    encoded.to_bytes()
 }
 
-/// Encodes headers and ifd::values for entries
+/// Encodes headers and ifd::values for entries, along with any extra entries
+/// added to the buffer, and returns the number of entries written.
+///
+/// Extra entries with a tag the image already uses are not written, nor are
+/// extra entries with 64-bit values too large for a classic TIFF. As the
+/// resolution is required, the default resolution is written if none was
+/// given.
 pub(crate) fn encode_ifds<E: EncodeEndianness, I: ExactSizeIterator<Item = ifd::Entry>>(
     wrt: &mut TiffEncodeBuffer<E>,
     ifds: I,
-) {
-    let field_count = ifds.len();
+) -> usize {
+    let mut entries: Vec<ifd::RawEntry> = ifds.map(ifd::RawEntry::from).collect();
+    let format = wrt.format();
+    let extra_entries = wrt
+        .take_extra_entries()
+        .into_iter()
+        .filter_map(|entry| match format {
+            ifd::Format::Classic => entry.into_classic(),
+            ifd::Format::BigTiff => Some(entry),
+        });
+    let default_resolution = Resolution::default()
+        .ifd_entries()
+        .into_iter()
+        .map(ifd::RawEntry::from);
+    for extra_entry in extra_entries.chain(default_resolution) {
+        if entries.iter().all(|entry| entry.tag() != extra_entry.tag()) {
+            entries.push(extra_entry);
+        }
+    }
+    entries.sort_by_key(ifd::RawEntry::tag);
+
+    let field_count = entries.len();
     let ifd_inx = wrt.append_new_ifd(field_count);
 
-    for (entry_num, entry) in ifds.enumerate() {
+    for (entry_num, entry) in entries.iter().enumerate() {
         let value_offset = wrt.append_ifd_value(entry.values());
         wrt.get_ifd_at(ifd_inx, field_count)
             .get_entry(entry_num)
-            .set_all(&entry, value_offset);
+            .set_all(entry, &value_offset);
     }
+
+    field_count
 }
 
-pub(crate) mod private {
-    use crate::types::{Long, Short};
+/// An encoder that also writes extra entries, such as metadata or private
+/// tags read from another file. See [`ImageEncoder::with_metadata`] and
+/// [`ImageEncoder::with_raw_entries`].
+///
+/// When calls are chained, entries from later calls replace entries with the
+/// same tag from earlier calls.
+pub struct WithEntries<T> {
+    encoder: T,
+    entries: Vec<ifd::RawEntry>,
+}
+
+impl<T: ImageEncoder> ImageEncoder for WithEntries<T> {}
 
-    use super::{buffer::TiffEncodeBuffer, EncodeEndianness};
+impl<T: ImageEncoder> private::ImageEncoderImpl for WithEntries<T> {
+    type Endianness = T::Endianness;
+
+    fn append_image_to_buffer(
+        &self,
+        wrt: &mut TiffEncodeBuffer<Self::Endianness>,
+    ) -> private::IfdInfo {
+        wrt.add_extra_entries(self.entries.iter().cloned());
+        self.encoder.append_image_to_buffer(wrt)
+    }
+}
+
+pub(crate) mod private {
+    use super::{buffer::TiffEncodeBuffer, layout::TileSize, EncodeEndianness};
 
     pub struct IfdInfo {
         pub(crate) inx: usize,
         pub(crate) entry_count: usize,
     }
 
-    pub struct EncodeResult {
-        pub(crate) image_strip_offsets: Vec<Short>,
-        pub(crate) image_strip_bytecounts: Vec<Long>,
+    pub enum EncodeResult {
+        Strips {
+            image_strip_offsets: Vec<u64>,
+            image_strip_bytecounts: Vec<u64>,
+            rows_per_strip: u64,
+        },
+        Tiles {
+            tile_size: TileSize,
+            image_tile_offsets: Vec<u64>,
+            image_tile_bytecounts: Vec<u64>,
+        },
     }
 
     pub trait ImageEncoderImpl {
@@ -103,3 +251,259 @@ pub(crate) mod private {
         fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<Self::Endianness>) -> IfdInfo;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::{
+        compression::{Lzw, NoCompression},
+        encode_bigtiff_images,
+        grayscale_8bit::*,
+        rgb::RGBImageEncoder,
+        EncodeEndianness, ImageEncoder, Layout,
+    };
+    use crate::{
+        colors::{Grayscale8Bit, RGB},
+        decode::decode_images,
+        ifd,
+        test_util::{assert_decodes_to, assert_same_pixels, decoded_as},
+        types::{SRational, URational},
+        Image,
+    };
+
+    /// Returns the count and the value or offset field of the entry with
+    /// `tag` in the first IFD of a little endian BigTIFF.
+    fn bigtiff_entry(encoded: &[u8], tag: u16) -> (u64, [u8; 8]) {
+        let read_u64 = |inx: usize| u64::from_le_bytes(encoded[inx..inx + 8].try_into().unwrap());
+        let ifd_inx = read_u64(8) as usize;
+        (0..read_u64(ifd_inx) as usize)
+            .map(|entry| ifd_inx + 8 + entry * 20)
+            .find(|&inx| u16::from_le_bytes([encoded[inx], encoded[inx + 1]]) == tag)
+            .map(|inx| {
+                (
+                    read_u64(inx + 4),
+                    encoded[inx + 12..inx + 20].try_into().unwrap(),
+                )
+            })
+            .expect("tag not found")
+    }
+
+    #[test]
+    fn bigtiff_header() {
+        let image = Image::new(vec![Grayscale8Bit(1); 4], 2, 2);
+        let little = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            BlackIsZero,
+        )
+        .encode_bigtiff();
+        assert_eq!(little[..8], [b'I', b'I', 43, 0, 8, 0, 0, 0]);
+        let big =
+            Grayscale8BitImageEncoder::<BigEndian, _, _>::new(&image, NoCompression, BlackIsZero)
+                .encode_bigtiff();
+        assert_eq!(big[..8], [b'M', b'M', 0, 43, 0, 8, 0, 0]);
+        assert_decodes_to(&little, &image);
+        assert_decodes_to(&big, &image);
+    }
+
+    #[test]
+    fn bigtiff_round_trip_of_multiple_images() {
+        let first = Image::new((0..=255).map(Grayscale8Bit).collect(), 16, 16);
+        let second = Image::new((0..35).map(Grayscale8Bit).collect(), 5, 7);
+        let encoders = [
+            Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(&first, Lzw, BlackIsZero)
+                .with_layout(Layout::RowsPerStrip(5)),
+            Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(&second, Lzw, BlackIsZero),
+        ];
+
+        let decoded = decode_images(&encode_bigtiff_images(encoders.iter())).unwrap();
+        assert!(decoded.errors.is_empty(), "{:?}", decoded.errors);
+        assert_eq!(decoded.pages.len(), 2);
+        for (page, image) in decoded.pages.into_iter().zip([&first, &second]) {
+            assert_same_pixels(&decoded_as(page.image), image);
+        }
+    }
+
+    #[test]
+    fn bigtiff_values_inline_and_out_of_line() {
+        // Rows of 12 bytes, one row per strip
+        let rgb_image = |height| Image::new(vec![RGB::new(1, 2, 3); 4 * height], 4, height);
+
+        // Four SHORT byte counts fit in the entry, and five don't
+        let image = rgb_image(4);
+        let encoded = RGBImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+            .with_layout(Layout::RowsPerStrip(1))
+            .encode_bigtiff();
+        assert_eq!(bigtiff_entry(&encoded, 258), (3, [8, 0, 8, 0, 8, 0, 0, 0]));
+        assert_eq!(
+            bigtiff_entry(&encoded, 279),
+            (4, [12, 0, 12, 0, 12, 0, 12, 0])
+        );
+        assert_decodes_to(&encoded, &image);
+
+        let image = rgb_image(5);
+        let encoded = RGBImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+            .with_layout(Layout::RowsPerStrip(1))
+            .encode_bigtiff();
+        let (count, offset) = bigtiff_entry(&encoded, 279);
+        let offset = u64::from_le_bytes(offset) as usize;
+        assert_eq!(count, 5);
+        assert_eq!(
+            encoded[offset..offset + 10],
+            [12, 0, 12, 0, 12, 0, 12, 0, 12, 0]
+        );
+        assert_decodes_to(&encoded, &image);
+    }
+
+    fn encode_with_raw_entries<E: EncodeEndianness>(
+        image: &Image<Grayscale8Bit>,
+        raw_entries: Vec<ifd::RawEntry>,
+    ) -> Vec<u8> {
+        Grayscale8BitImageEncoder::<E, _, _>::new(image, NoCompression, BlackIsZero)
+            .with_raw_entries(raw_entries)
+            .encode()
+    }
+
+    #[test]
+    fn raw_entries_round_trip() {
+        let image = Image::new(vec![Grayscale8Bit(1); 4], 2, 2);
+        let raw_entries = vec![
+            ifd::RawEntry::new(
+                65000,
+                ifd::Type::ASCII,
+                ifd::Values::Bytes(b"private\0".to_vec()),
+            ),
+            ifd::RawEntry::new(65001, ifd::Type::SShort, ifd::Values::SShorts(vec![-1])),
+            ifd::RawEntry::new(
+                65002,
+                ifd::Type::Rational,
+                ifd::Values::Rationals(vec![URational::new(1, 3)]),
+            ),
+            ifd::RawEntry::new(
+                65003,
+                ifd::Type::SRational,
+                ifd::Values::SRationals(vec![SRational {
+                    numerator: -1,
+                    denominator: 3,
+                }]),
+            ),
+            ifd::RawEntry::new(
+                65004,
+                ifd::Type::Undefined,
+                ifd::Values::Undefined((0..9).collect()),
+            ),
+        ];
+        // Entries the encoder writes itself are ignored
+        let image_width = ifd::RawEntry::new(256, ifd::Type::Long, ifd::Values::Longs(vec![9]));
+        let mut with_image_width = raw_entries.clone();
+        with_image_width.push(image_width);
+
+        for encode in [
+            encode_with_raw_entries::<LittleEndian> as fn(&_, _) -> _,
+            encode_with_raw_entries::<BigEndian>,
+        ] {
+            let encoded = encode(&image, with_image_width.clone());
+            let mut decoded = decode_images(&encoded).unwrap();
+            assert!(decoded.errors.is_empty(), "{:?}", decoded.errors);
+            let page = decoded.pages.remove(0);
+            assert_eq!(page.raw_entries, raw_entries);
+            assert_same_pixels(&decoded_as(page.image), &image);
+            // Writing the decoded entries back gives the same file
+            assert_eq!(encode(&image, page.raw_entries), encoded);
+        }
+    }
+
+    #[test]
+    fn bigtiff_raw_entries_inline_and_out_of_line() {
+        let image = Image::new(vec![Grayscale8Bit(1); 4], 2, 2);
+        let raw_entries = vec![
+            // 8 bytes fit in the entry
+            ifd::RawEntry::new(65000, ifd::Type::Byte, ifd::Values::Bytes(vec![1; 8])),
+            ifd::RawEntry::new(65001, ifd::Type::Byte, ifd::Values::Bytes(vec![2; 9])),
+            ifd::RawEntry::new(65002, ifd::Type::Long, ifd::Values::Longs(vec![3, 4])),
+            ifd::RawEntry::new(65003, ifd::Type::Long8, ifd::Values::Long8s(vec![1 << 40])),
+            ifd::RawEntry::new(65004, ifd::Type::Long8, ifd::Values::Long8s(vec![5, 6])),
+        ];
+        let encoded = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            BlackIsZero,
+        )
+        .with_raw_entries(raw_entries.clone())
+        .encode_bigtiff();
+
+        assert_eq!(bigtiff_entry(&encoded, 65000), (8, [1; 8]));
+        assert_eq!(
+            bigtiff_entry(&encoded, 65002),
+            (2, [3, 0, 0, 0, 4, 0, 0, 0])
+        );
+        assert_eq!(
+            bigtiff_entry(&encoded, 65003),
+            (1, (1u64 << 40).to_le_bytes())
+        );
+        let (count, offset) = bigtiff_entry(&encoded, 65001);
+        let offset = u64::from_le_bytes(offset) as usize;
+        assert_eq!(count, 9);
+        assert_eq!(encoded[offset..offset + 9], [2; 9]);
+        let (count, offset) = bigtiff_entry(&encoded, 65004);
+        let offset = u64::from_le_bytes(offset) as usize;
+        assert_eq!(count, 2);
+        assert_eq!(
+            encoded[offset..offset + 16],
+            [5, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        let decoded = decode_images(&encoded).unwrap();
+        assert!(decoded.errors.is_empty());
+        assert_eq!(decoded.pages[0].raw_entries, raw_entries);
+    }
+
+    #[test]
+    fn classic_narrows_or_skips_64_bit_raw_entries() {
+        let image = Image::new(vec![Grayscale8Bit(1); 4], 2, 2);
+        let raw_entries = vec![
+            ifd::RawEntry::new(65000, ifd::Type::Long8, ifd::Values::Long8s(vec![5, 6])),
+            ifd::RawEntry::new(65001, ifd::Type::Long8, ifd::Values::Long8s(vec![1 << 32])),
+        ];
+        let encoder = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            BlackIsZero,
+        )
+        .with_raw_entries(raw_entries.clone());
+
+        let classic = decode_images(&encoder.encode()).unwrap();
+        assert_eq!(
+            classic.pages[0].raw_entries,
+            [ifd::RawEntry::new(
+                65000,
+                ifd::Type::Long,
+                ifd::Values::Longs(vec![5, 6])
+            )]
+        );
+        let bigtiff = decode_images(&encoder.encode_bigtiff()).unwrap();
+        assert_eq!(bigtiff.pages[0].raw_entries, raw_entries);
+    }
+
+    #[test]
+    fn offset_entries_are_not_kept() {
+        let image = Image::new(vec![Grayscale8Bit(1); 4], 2, 2);
+        let private = ifd::RawEntry::new(65000, ifd::Type::Short, ifd::Values::Shorts(vec![7]));
+        let encoder = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            BlackIsZero,
+        )
+        .with_raw_entries(vec![
+            // ExifIFD
+            ifd::RawEntry::new(34665, ifd::Type::Long, ifd::Values::Longs(vec![8])),
+            ifd::RawEntry::new(65001, ifd::Type::Ifd8, ifd::Values::Ifd8s(vec![8])),
+            private.clone(),
+        ]);
+
+        let decoded = decode_images(&encoder.encode_bigtiff()).unwrap();
+        assert!(decoded.errors.is_empty());
+        assert_eq!(decoded.pages[0].raw_entries, [private]);
+    }
+}
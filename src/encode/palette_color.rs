@@ -1,19 +1,20 @@
-use std::{marker::PhantomData, slice::ChunksExact};
+use std::marker::PhantomData;
 
 use crate::{
     colors,
     compression::Compression,
     encode::{encode_ifds, private::EncodeResult},
     ifd,
-    types::{Short, URational},
+    types::Short,
     Image,
 };
 
 use super::{
     buffer::TiffEncodeBuffer,
     compression::HalfBytePacker,
+    layout::encode_image_data,
     private::{IfdInfo, ImageEncoderImpl},
-    EncodeEndianness, ImageEncoder,
+    EncodeEndianness, ImageEncoder, Layout,
 };
 
 pub struct PaletteColorImageEncoder<'a, E, C>
@@ -22,6 +23,7 @@ where
 {
     image: &'a Image<colors::PaletteColor<'a>>,
     image_compressor: C,
+    layout: Layout,
     endianness: PhantomData<E>,
 }
 
@@ -32,9 +34,16 @@ impl<'a, E: EncodeEndianness, C: Compression<colors::PaletteColor<'a>>>
         Self {
             image,
             image_compressor: compression,
+            layout: Layout::default(),
             endianness: PhantomData,
         }
     }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
 }
 
 impl<'a, E, C> ImageEncoder for PaletteColorImageEncoder<'a, E, C>
@@ -52,19 +61,17 @@ where
     type Endianness = E;
 
     fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
-        let EncodeResult {
-            image_strip_offsets,
-            image_strip_bytecounts,
-        } = encode_palettized_img(
+        let image_data = encode_palettized_img(
             wrt,
-            self.image.rows(),
+            self.image,
+            self.layout,
             &self.image_compressor,
             self.image.bits_per_palette_sample() as u8,
         );
 
         let ifd_inx = wrt.align_and_get_len();
 
-        let ifd_entries = [
+        let mut ifd_entries = vec![
             ifd::Entry::new(
                 ifd::Tag::ImageWidth,
                 ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
@@ -87,42 +94,14 @@ where
                     ifd::tags::PhotometricInterpretation::PaletteColor as Short,
                 ]),
             ),
-            ifd::Entry::new(
-                ifd::Tag::StripOffsets,
-                ifd::Values::Shorts(image_strip_offsets),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::RowsPerStrip,
-                ifd::Values::Shorts(vec![self.image.height().try_into().unwrap()]),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::StripByteCounts,
-                ifd::Values::Longs(image_strip_bytecounts),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::XResolution,
-                ifd::Values::Rationals(vec![URational::new(1, 1)]),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::YResolution,
-                ifd::Values::Rationals(vec![URational::new(1, 1)]),
-            ),
             ifd::Entry::new(
                 ifd::Tag::ColorMap,
                 ifd::Values::Shorts(self.image.get_colormap().create_colormap_vec()),
             ),
         ];
-        let entry_count = ifd_entries.len();
-
-        debug_assert!(
-            ifd_entries
-                .iter()
-                .zip(ifd_entries.iter().skip(1))
-                .all(|(prev, next)| prev.tag() <= next.tag()),
-            "IFD entries are not sorted by tag"
-        );
-
-        encode_ifds(wrt, ifd_entries.into_iter());
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
 
         IfdInfo {
             inx: ifd_inx,
@@ -133,7 +112,8 @@ where
 
 fn encode_palettized_img<'a, C, E>(
     wrt: &mut TiffEncodeBuffer<E>,
-    rows: ChunksExact<'_, colors::PaletteColor>,
+    image: &Image<colors::PaletteColor<'a>>,
+    layout: Layout,
     image_compressor: &C,
     bits_per_sample: u8,
 ) -> EncodeResult
@@ -141,20 +121,14 @@ where
     C: Compression<colors::PaletteColor<'a>>,
     E: EncodeEndianness,
 {
-    let row_inx = wrt.align_and_get_len();
-
     if bits_per_sample == 8 {
-        image_compressor.encode(wrt, rows.flatten().map(colors::PaletteColor::get_inx));
+        encode_image_data(wrt, image, layout, 8, image_compressor, |row| {
+            row.iter().map(colors::PaletteColor::get_inx)
+        })
     } else {
         // 4 bits per sample
-        image_compressor.encode(
-            wrt,
-            rows.flat_map(|row| HalfBytePacker::new(row.iter().map(colors::PaletteColor::get_inx))),
-        );
-    }
-
-    EncodeResult {
-        image_strip_offsets: vec![row_inx.try_into().unwrap()],
-        image_strip_bytecounts: vec![(wrt.len() - row_inx).try_into().unwrap()],
+        encode_image_data(wrt, image, layout, 4, image_compressor, |row| {
+            HalfBytePacker::new(row.iter().map(colors::PaletteColor::get_inx))
+        })
     }
 }
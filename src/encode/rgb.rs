@@ -1,18 +1,19 @@
-use std::{marker::PhantomData, slice::ChunksExact};
+use std::marker::PhantomData;
 
 use crate::{
     colors,
     compression::Compression,
     encode::{encode_ifds, private::EncodeResult},
     ifd,
-    types::{Short, URational},
+    types::Short,
     Image,
 };
 
 use super::{
     buffer::TiffEncodeBuffer,
+    layout::{encode_image_data, encode_planar_image_data},
     private::{IfdInfo, ImageEncoderImpl},
-    EncodeEndianness, ImageEncoder,
+    EncodeEndianness, ImageEncoder, Layout, PlanarConfiguration,
 };
 
 pub struct RGBImageEncoder<'a, E, C>
@@ -21,6 +22,8 @@ where
 {
     image: &'a Image<colors::RGB>,
     image_compressor: C,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
     endianness: PhantomData<E>,
 }
 
@@ -29,10 +32,24 @@ impl<'a, E: EncodeEndianness, C: Compression<colors::RGB>> RGBImageEncoder<'a, E
         Self {
             image,
             image_compressor: compression,
-
+            layout: Layout::default(),
+            planar_configuration: PlanarConfiguration::default(),
             endianness: PhantomData,
         }
     }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets whether the samples of each pixel are stored together or in
+    /// separate planes.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
 }
 
 impl<'a, E: EncodeEndianness, C: Compression<colors::RGB>> ImageEncoder
@@ -46,14 +63,17 @@ impl<'a, E: EncodeEndianness, C: Compression<colors::RGB>> ImageEncoderImpl
     type Endianness = E;
 
     fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
-        let EncodeResult {
-            image_strip_offsets,
-            image_strip_bytecounts,
-        } = encode_rgb_img(wrt, self.image.rows(), &self.image_compressor);
+        let image_data = encode_rgb_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.planar_configuration,
+            &self.image_compressor,
+        );
 
         let ifd_inx = wrt.align_and_get_len();
 
-        let ifd_entries = [
+        let mut ifd_entries = vec![
             ifd::Entry::new(
                 ifd::Tag::ImageWidth,
                 ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
@@ -71,39 +91,15 @@ impl<'a, E: EncodeEndianness, C: Compression<colors::RGB>> ImageEncoderImpl
                 ifd::Tag::PhotometricInterpretation,
                 ifd::Values::Shorts(vec![ifd::tags::PhotometricInterpretation::RGB as Short]),
             ),
-            ifd::Entry::new(
-                ifd::Tag::StripOffsets,
-                ifd::Values::Shorts(image_strip_offsets),
-            ),
             ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![3])),
             ifd::Entry::new(
-                ifd::Tag::RowsPerStrip,
-                ifd::Values::Shorts(vec![self.image.height().try_into().unwrap()]),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::StripByteCounts,
-                ifd::Values::Longs(image_strip_bytecounts),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::XResolution,
-                ifd::Values::Rationals(vec![URational::new(1, 1)]),
-            ),
-            ifd::Entry::new(
-                ifd::Tag::YResolution,
-                ifd::Values::Rationals(vec![URational::new(1, 1)]),
+                ifd::Tag::PlanarConfiguration,
+                ifd::Values::Shorts(vec![self.planar_configuration as Short]),
             ),
         ];
-        let entry_count = ifd_entries.len();
-
-        debug_assert!(
-            ifd_entries
-                .iter()
-                .zip(ifd_entries.iter().skip(1))
-                .all(|(prev, next)| prev.tag() <= next.tag()),
-            "IFD entries are not sorted by tag"
-        );
-
-        encode_ifds(wrt, ifd_entries.into_iter());
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
 
         IfdInfo {
             inx: ifd_inx,
@@ -114,18 +110,27 @@ impl<'a, E: EncodeEndianness, C: Compression<colors::RGB>> ImageEncoderImpl
 
 fn encode_rgb_img<C: Compression<colors::RGB>, E: EncodeEndianness>(
     wrt: &mut TiffEncodeBuffer<E>,
-    rows: ChunksExact<'_, colors::RGB>,
+    image: &Image<colors::RGB>,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
     image_compressor: &C,
 ) -> EncodeResult {
-    let row_inx = wrt.align_and_get_len();
-
-    image_compressor.encode(
-        wrt,
-        rows.flatten().flat_map(|pixel| [pixel.r, pixel.g, pixel.b]),
-    );
-
-    EncodeResult {
-        image_strip_offsets: vec![row_inx.try_into().unwrap()],
-        image_strip_bytecounts: vec![(wrt.len() - row_inx).try_into().unwrap()],
+    match planar_configuration {
+        PlanarConfiguration::Chunky => {
+            encode_image_data(wrt, image, layout, 24, image_compressor, |row| {
+                row.iter().flat_map(|pixel| [pixel.r, pixel.g, pixel.b])
+            })
+        }
+        PlanarConfiguration::Planar => encode_planar_image_data(
+            wrt,
+            image,
+            layout,
+            &[8, 8, 8],
+            image_compressor,
+            |row, plane| {
+                row.iter()
+                    .map(move |pixel| [pixel.r, pixel.g, pixel.b][plane])
+            },
+        ),
     }
 }
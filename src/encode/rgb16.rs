@@ -0,0 +1,207 @@
+use std::marker::PhantomData;
+
+use crate::{
+    colors,
+    compression::Compression,
+    encode::{encode_ifds, private::EncodeResult},
+    ifd,
+    types::Short,
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::{encode_image_data, encode_planar_image_data},
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout, PlanarConfiguration,
+};
+
+pub struct RGB16ImageEncoder<'a, E, C>
+where
+    C: Compression<colors::RGB16>,
+{
+    image: &'a Image<colors::RGB16>,
+    image_compressor: C,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGB16>> RGB16ImageEncoder<'a, E, C> {
+    pub fn new(image: &'a Image<colors::RGB16>, compression: C) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            layout: Layout::default(),
+            planar_configuration: PlanarConfiguration::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets whether the samples of each pixel are stored together or in
+    /// separate planes.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGB16>> ImageEncoder
+    for RGB16ImageEncoder<'a, E, C>
+{
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGB16>> ImageEncoderImpl
+    for RGB16ImageEncoder<'a, E, C>
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_rgb16_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.planar_configuration,
+            &self.image_compressor,
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::BitsPerSample,
+                ifd::Values::Shorts(vec![16, 16, 16]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![ifd::tags::PhotometricInterpretation::RGB as Short]),
+            ),
+            ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![3])),
+            ifd::Entry::new(
+                ifd::Tag::PlanarConfiguration,
+                ifd::Values::Shorts(vec![self.planar_configuration as Short]),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+fn encode_rgb16_img<C: Compression<colors::RGB16>, E: EncodeEndianness>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &Image<colors::RGB16>,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    image_compressor: &C,
+) -> EncodeResult {
+    match planar_configuration {
+        PlanarConfiguration::Chunky => {
+            encode_image_data(wrt, image, layout, 48, image_compressor, |row| {
+                row.iter().flat_map(|pixel| {
+                    // Samples are written in the byte order of the file
+                    let mut samples = [0; 6];
+                    E::write_u16_into(&[pixel.r, pixel.g, pixel.b], &mut samples);
+                    samples
+                })
+            })
+        }
+        PlanarConfiguration::Planar => encode_planar_image_data(
+            wrt,
+            image,
+            layout,
+            &[16, 16, 16],
+            image_compressor,
+            |row, plane| {
+                row.iter().flat_map(move |pixel| {
+                    let mut sample = [0; 2];
+                    E::write_u16(&mut sample, [pixel.r, pixel.g, pixel.b][plane]);
+                    sample
+                })
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::RGB16ImageEncoder;
+    use crate::{
+        colors::RGB16,
+        encode::{
+            compression::{Lzw, NoCompression},
+            ImageEncoder, Layout, PlanarConfiguration,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    #[test]
+    fn samples_are_written_in_file_byte_order() {
+        let image = Image::new(vec![RGB16::new(0x0102, 0x0304, 0x0506)], 1, 1);
+        let encoded = RGB16ImageEncoder::<LittleEndian, _>::new(&image, NoCompression).encode();
+        assert_eq!(encoded[8..14], [2, 1, 4, 3, 6, 5]);
+        let encoded = RGB16ImageEncoder::<BigEndian, _>::new(&image, NoCompression).encode();
+        assert_eq!(encoded[8..14], [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn round_trip() {
+        // High and low bytes differ in every sample
+        let pixels = (0..7 * 5)
+            .map(|i| {
+                RGB16::new(
+                    0x0102 + i * 0x0703,
+                    0xFEDC - i * 0x0311,
+                    0x1234 + i * 0x0101,
+                )
+            })
+            .collect();
+        let image = Image::new(pixels, 7, 5);
+        for planar_configuration in [PlanarConfiguration::Chunky, PlanarConfiguration::Planar] {
+            for encoded in [
+                RGB16ImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                    .with_planar_configuration(planar_configuration)
+                    .encode(),
+                RGB16ImageEncoder::<BigEndian, _>::new(&image, NoCompression)
+                    .with_planar_configuration(planar_configuration)
+                    .encode(),
+                RGB16ImageEncoder::<LittleEndian, _>::new(&image, Lzw)
+                    .with_planar_configuration(planar_configuration)
+                    .with_layout(Layout::RowsPerStrip(2))
+                    .encode(),
+                RGB16ImageEncoder::<BigEndian, _>::new(&image, Lzw)
+                    .with_planar_configuration(planar_configuration)
+                    .with_layout(Layout::RowsPerStrip(2))
+                    .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::marker::PhantomData;
+
+use crate::{
+    colors,
+    compression::Compression,
+    encode::{encode_ifds, private::EncodeResult},
+    ifd,
+    types::Short,
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::{encode_image_data, encode_planar_image_data},
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout, PlanarConfiguration,
+};
+
+pub struct RGBAImageEncoder<'a, E, C>
+where
+    C: Compression<colors::RGBA>,
+{
+    image: &'a Image<colors::RGBA>,
+    image_compressor: C,
+    alpha: colors::Alpha,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGBA>> RGBAImageEncoder<'a, E, C> {
+    /// `alpha` is whether the colors of `image` have been premultiplied by
+    /// their alpha.
+    pub fn new(image: &'a Image<colors::RGBA>, compression: C, alpha: colors::Alpha) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            alpha,
+            layout: Layout::default(),
+            planar_configuration: PlanarConfiguration::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets whether the samples of each pixel are stored together or in
+    /// separate planes.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGBA>> ImageEncoder
+    for RGBAImageEncoder<'a, E, C>
+{
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGBA>> ImageEncoderImpl
+    for RGBAImageEncoder<'a, E, C>
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_rgba_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.planar_configuration,
+            &self.image_compressor,
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::BitsPerSample,
+                ifd::Values::Shorts(vec![8, 8, 8, 8]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![ifd::tags::PhotometricInterpretation::RGB as Short]),
+            ),
+            ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![4])),
+            ifd::Entry::new(
+                ifd::Tag::PlanarConfiguration,
+                ifd::Values::Shorts(vec![self.planar_configuration as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ExtraSamples,
+                ifd::Values::Shorts(vec![ifd::tags::ExtraSamples::from(self.alpha) as Short]),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+fn encode_rgba_img<C: Compression<colors::RGBA>, E: EncodeEndianness>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &Image<colors::RGBA>,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    image_compressor: &C,
+) -> EncodeResult {
+    match planar_configuration {
+        PlanarConfiguration::Chunky => {
+            encode_image_data(wrt, image, layout, 32, image_compressor, |row| {
+                row.iter()
+                    .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a])
+            })
+        }
+        PlanarConfiguration::Planar => encode_planar_image_data(
+            wrt,
+            image,
+            layout,
+            &[8, 8, 8, 8],
+            image_compressor,
+            |row, plane| {
+                row.iter()
+                    .map(move |pixel| [pixel.r, pixel.g, pixel.b, pixel.a][plane])
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::RGBAImageEncoder;
+    use crate::{
+        colors::{Alpha, RGBA},
+        encode::{
+            compression::{Lzw, NoCompression},
+            ImageEncoder, Layout, PlanarConfiguration,
+        },
+        test_util::assert_decodes_to,
+        Image,
+    };
+
+    #[test]
+    fn round_trip() {
+        let pixels = (0..7 * 5)
+            .map(|i| RGBA::new(i * 7, 255 - i, i * 3, 128 + i))
+            .collect();
+        let image = Image::new(pixels, 7, 5);
+        for planar_configuration in [PlanarConfiguration::Chunky, PlanarConfiguration::Planar] {
+            for encoded in [
+                RGBAImageEncoder::<LittleEndian, _>::new(&image, NoCompression, Alpha::Associated)
+                    .with_planar_configuration(planar_configuration)
+                    .encode(),
+                RGBAImageEncoder::<BigEndian, _>::new(&image, Lzw, Alpha::Unassociated)
+                    .with_planar_configuration(planar_configuration)
+                    .with_layout(Layout::RowsPerStrip(2))
+                    .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
+}
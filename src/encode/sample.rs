@@ -0,0 +1,361 @@
+use std::marker::PhantomData;
+
+use crate::{
+    colors::{self, Sample},
+    compression::Compression,
+    encode::{encode_ifds, private::EncodeResult},
+    ifd,
+    types::Short,
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::{encode_image_data, encode_planar_image_data},
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout, PlanarConfiguration,
+};
+
+pub struct SampleGrayscaleImageEncoder<'a, E, C, T>
+where
+    C: Compression<colors::SampleGrayscale<T>>,
+    T: Sample,
+{
+    image: &'a Image<colors::SampleGrayscale<T>>,
+    image_compressor: C,
+    layout: Layout,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E, C, T> SampleGrayscaleImageEncoder<'a, E, C, T>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::SampleGrayscale<T>>,
+    T: Sample,
+{
+    pub fn new(image: &'a Image<colors::SampleGrayscale<T>>, compression: C) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            layout: Layout::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+impl<'a, E, C, T> ImageEncoder for SampleGrayscaleImageEncoder<'a, E, C, T>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::SampleGrayscale<T>>,
+    T: Sample,
+{
+}
+
+impl<'a, E, C, T> ImageEncoderImpl for SampleGrayscaleImageEncoder<'a, E, C, T>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::SampleGrayscale<T>>,
+    T: Sample,
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_image_data(
+            wrt,
+            self.image,
+            self.layout,
+            T::BITS as usize,
+            &self.image_compressor,
+            |row| row.iter().flat_map(|pixel| pixel.0.encode::<E>()),
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(ifd::Tag::BitsPerSample, ifd::Values::Shorts(vec![T::BITS])),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![
+                    ifd::tags::PhotometricInterpretation::BlackIsZero as Short,
+                ]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::SampleFormat,
+                ifd::Values::Shorts(vec![T::FORMAT as Short]),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+pub struct SampleRGBImageEncoder<'a, E, C, T>
+where
+    C: Compression<colors::SampleRGB<T>>,
+    T: Sample,
+{
+    image: &'a Image<colors::SampleRGB<T>>,
+    image_compressor: C,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E, C, T> SampleRGBImageEncoder<'a, E, C, T>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::SampleRGB<T>>,
+    T: Sample,
+{
+    pub fn new(image: &'a Image<colors::SampleRGB<T>>, compression: C) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            layout: Layout::default(),
+            planar_configuration: PlanarConfiguration::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets whether the samples of each pixel are stored together or in
+    /// separate planes.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
+}
+
+impl<'a, E, C, T> ImageEncoder for SampleRGBImageEncoder<'a, E, C, T>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::SampleRGB<T>>,
+    T: Sample,
+{
+}
+
+impl<'a, E, C, T> ImageEncoderImpl for SampleRGBImageEncoder<'a, E, C, T>
+where
+    E: EncodeEndianness,
+    C: Compression<colors::SampleRGB<T>>,
+    T: Sample,
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_sample_rgb_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.planar_configuration,
+            &self.image_compressor,
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::BitsPerSample,
+                ifd::Values::Shorts(vec![T::BITS; 3]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![ifd::tags::PhotometricInterpretation::RGB as Short]),
+            ),
+            ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![3])),
+            ifd::Entry::new(
+                ifd::Tag::PlanarConfiguration,
+                ifd::Values::Shorts(vec![self.planar_configuration as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::SampleFormat,
+                ifd::Values::Shorts(vec![T::FORMAT as Short; 3]),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+fn encode_sample_rgb_img<C, E, T>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &Image<colors::SampleRGB<T>>,
+    layout: Layout,
+    planar_configuration: PlanarConfiguration,
+    image_compressor: &C,
+) -> EncodeResult
+where
+    C: Compression<colors::SampleRGB<T>>,
+    E: EncodeEndianness,
+    T: Sample,
+{
+    let bits = T::BITS as usize;
+    match planar_configuration {
+        PlanarConfiguration::Chunky => {
+            encode_image_data(wrt, image, layout, bits * 3, image_compressor, |row| {
+                row.iter()
+                    .flat_map(|pixel| [pixel.r, pixel.g, pixel.b])
+                    .flat_map(T::encode::<E>)
+            })
+        }
+        PlanarConfiguration::Planar => encode_planar_image_data(
+            wrt,
+            image,
+            layout,
+            &[bits; 3],
+            image_compressor,
+            |row, plane| {
+                row.iter()
+                    .flat_map(move |pixel| [pixel.r, pixel.g, pixel.b][plane].encode::<E>())
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::{SampleGrayscaleImageEncoder, SampleRGBImageEncoder};
+    use crate::{
+        colors::{f16, Sample, SampleGrayscale, SampleRGB},
+        encode::{
+            compression::{Lzw, NoCompression},
+            ImageEncoder, Layout, PlanarConfiguration,
+        },
+        test_util::{assert_decodes_to, DecodesTo},
+        Image,
+    };
+
+    fn grayscale_round_trip<T>(samples: [T; 6])
+    where
+        T: Sample + PartialEq,
+        SampleGrayscale<T>: DecodesTo<ViewAs = SampleGrayscale<T>>,
+    {
+        let image = Image::new(samples.map(SampleGrayscale).to_vec(), 3, 2);
+        for encoded in [
+            SampleGrayscaleImageEncoder::<LittleEndian, _, _>::new(&image, NoCompression).encode(),
+            SampleGrayscaleImageEncoder::<BigEndian, _, _>::new(&image, Lzw)
+                .with_layout(Layout::RowsPerStrip(1))
+                .encode(),
+        ] {
+            assert_decodes_to(&encoded, &image);
+        }
+    }
+
+    fn rgb_round_trip<T>(samples: [T; 6])
+    where
+        T: Sample + PartialEq,
+        SampleRGB<T>: DecodesTo<ViewAs = SampleRGB<T>>,
+    {
+        let pixels = (0..6)
+            .map(|inx| SampleRGB::new(samples[inx], samples[(inx + 2) % 6], samples[(inx + 4) % 6]))
+            .collect();
+        let image = Image::new(pixels, 3, 2);
+        for planar_configuration in [PlanarConfiguration::Chunky, PlanarConfiguration::Planar] {
+            for encoded in [
+                SampleRGBImageEncoder::<LittleEndian, _, _>::new(&image, NoCompression)
+                    .with_planar_configuration(planar_configuration)
+                    .encode(),
+                SampleRGBImageEncoder::<BigEndian, _, _>::new(&image, Lzw)
+                    .with_planar_configuration(planar_configuration)
+                    .with_layout(Layout::RowsPerStrip(1))
+                    .encode(),
+            ] {
+                assert_decodes_to(&encoded, &image);
+            }
+        }
+    }
+
+    #[test]
+    fn float_round_trips() {
+        let samples = [0.0, -1.5, 0.1, 1e-40, f64::INFINITY, -65504.0];
+        grayscale_round_trip(samples.map(f16::from_f64));
+        grayscale_round_trip(samples.map(|sample| sample as f32));
+        grayscale_round_trip(samples);
+        rgb_round_trip(samples.map(f16::from_f64));
+        rgb_round_trip(samples.map(|sample| sample as f32));
+        rgb_round_trip(samples);
+    }
+
+    #[test]
+    fn signed_round_trips() {
+        grayscale_round_trip([0i8, -1, 1, i8::MIN, i8::MAX, -100]);
+        grayscale_round_trip([0i16, -1, 0x0102, i16::MIN, i16::MAX, -0x1234]);
+        grayscale_round_trip([0i32, -1, 0x0102_0304, i32::MIN, i32::MAX, -0x1234_5678]);
+        rgb_round_trip([0i8, -1, 1, i8::MIN, i8::MAX, -100]);
+        rgb_round_trip([0i16, -1, 0x0102, i16::MIN, i16::MAX, -0x1234]);
+        rgb_round_trip([0i32, -1, 0x0102_0304, i32::MIN, i32::MAX, -0x1234_5678]);
+    }
+
+    #[test]
+    fn samples_are_written_in_file_byte_order() {
+        let image = Image::new(vec![SampleGrayscale(1.5f32)], 1, 1);
+        let encoded =
+            SampleGrayscaleImageEncoder::<LittleEndian, _, _>::new(&image, NoCompression).encode();
+        assert_eq!(encoded[8..12], [0, 0, 0xC0, 0x3F]);
+        let encoded =
+            SampleGrayscaleImageEncoder::<BigEndian, _, _>::new(&image, NoCompression).encode();
+        assert_eq!(encoded[8..12], [0x3F, 0xC0, 0, 0]);
+
+        let image = Image::new(vec![SampleGrayscale(f16::from_f32(1.5))], 1, 1);
+        let encoded =
+            SampleGrayscaleImageEncoder::<BigEndian, _, _>::new(&image, NoCompression).encode();
+        assert_eq!(encoded[8..10], [0x3E, 0x00]);
+
+        let image = Image::new(vec![SampleGrayscale(-2i16)], 1, 1);
+        let encoded =
+            SampleGrayscaleImageEncoder::<LittleEndian, _, _>::new(&image, NoCompression).encode();
+        assert_eq!(encoded[8..10], [0xFE, 0xFF]);
+        let encoded =
+            SampleGrayscaleImageEncoder::<BigEndian, _, _>::new(&image, NoCompression).encode();
+        assert_eq!(encoded[8..10], [0xFF, 0xFE]);
+    }
+}
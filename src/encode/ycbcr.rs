@@ -0,0 +1,391 @@
+use std::marker::PhantomData;
+
+use crate::{
+    colors,
+    compression::Compression,
+    encode::{encode_ifds, private::EncodeResult},
+    ifd,
+    types::{Short, URational},
+    Image,
+};
+
+use super::{
+    buffer::TiffEncodeBuffer,
+    layout::encode_subsampled_image_data,
+    private::{IfdInfo, ImageEncoderImpl},
+    EncodeEndianness, ImageEncoder, Layout,
+};
+
+/// The red, green and blue luma coefficients of ITU-R BT.601.
+const LUMA_COEFFICIENTS: [f32; 3] = [0.299, 0.587, 0.114];
+
+/// How many pixels share each pair of chroma samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    /// 4:4:4, every pixel has its own chroma samples.
+    Full,
+    /// 4:2:2, chroma samples are shared by 2 horizontally adjacent pixels.
+    Horizontal,
+    /// 4:2:0, chroma samples are shared by 2x2 blocks of pixels.
+    ///
+    /// This is the default, but like [`ChromaSubsampling::Horizontal`] it is
+    /// lossy: each block is decoded with the average chroma of its pixels.
+    /// Use [`ChromaSubsampling::Full`] to keep the chroma of every pixel.
+    #[default]
+    HorizontalAndVertical,
+}
+
+impl ChromaSubsampling {
+    const fn factors(self) -> (usize, usize) {
+        match self {
+            ChromaSubsampling::Full => (1, 1),
+            ChromaSubsampling::Horizontal => (2, 1),
+            ChromaSubsampling::HorizontalAndVertical => (2, 2),
+        }
+    }
+}
+
+/// Encodes RGB images as YCbCr, which can store the chroma at a lower
+/// resolution than the luma.
+pub struct YCbCrImageEncoder<'a, E, C>
+where
+    C: Compression<colors::RGB>,
+{
+    image: &'a Image<colors::RGB>,
+    image_compressor: C,
+    layout: Layout,
+    subsampling: ChromaSubsampling,
+    endianness: PhantomData<E>,
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGB>> YCbCrImageEncoder<'a, E, C> {
+    pub fn new(image: &'a Image<colors::RGB>, compression: C) -> Self {
+        Self {
+            image,
+            image_compressor: compression,
+            layout: Layout::default(),
+            subsampling: ChromaSubsampling::default(),
+            endianness: PhantomData,
+        }
+    }
+
+    /// Sets how the image data is split into strips or tiles.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets how many pixels share each pair of chroma samples.
+    pub fn with_subsampling(mut self, subsampling: ChromaSubsampling) -> Self {
+        self.subsampling = subsampling;
+        self
+    }
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGB>> ImageEncoder
+    for YCbCrImageEncoder<'a, E, C>
+{
+}
+
+impl<'a, E: EncodeEndianness, C: Compression<colors::RGB>> ImageEncoderImpl
+    for YCbCrImageEncoder<'a, E, C>
+{
+    type Endianness = E;
+
+    fn append_image_to_buffer(&self, wrt: &mut TiffEncodeBuffer<E>) -> IfdInfo {
+        let image_data = encode_ycbcr_img(
+            wrt,
+            self.image,
+            self.layout,
+            self.subsampling,
+            &self.image_compressor,
+        );
+
+        let ifd_inx = wrt.align_and_get_len();
+
+        let (horizontal, vertical) = self.subsampling.factors();
+        let mut ifd_entries = vec![
+            ifd::Entry::new(
+                ifd::Tag::ImageWidth,
+                ifd::Values::Longs(vec![self.image.width().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ImageLength,
+                ifd::Values::Longs(vec![self.image.height().try_into().unwrap()]),
+            ),
+            ifd::Entry::new(ifd::Tag::BitsPerSample, ifd::Values::Shorts(vec![8, 8, 8])),
+            ifd::Entry::new(
+                ifd::Tag::Compression,
+                ifd::Values::Shorts(vec![self.image_compressor.compression_type_tag() as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::PhotometricInterpretation,
+                ifd::Values::Shorts(vec![ifd::tags::PhotometricInterpretation::YCbCr as Short]),
+            ),
+            ifd::Entry::new(ifd::Tag::SamplesPerPixel, ifd::Values::Shorts(vec![3])),
+            ifd::Entry::new(
+                ifd::Tag::YCbCrCoefficients,
+                ifd::Values::Rationals(vec![
+                    URational::new(299, 1000),
+                    URational::new(587, 1000),
+                    URational::new(114, 1000),
+                ]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::YCbCrSubSampling,
+                ifd::Values::Shorts(vec![horizontal as Short, vertical as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::YCbCrPositioning,
+                ifd::Values::Shorts(vec![ifd::tags::YCbCrPositioning::Centered as Short]),
+            ),
+            ifd::Entry::new(
+                ifd::Tag::ReferenceBlackWhite,
+                ifd::Values::Rationals(
+                    [0, 255, 128, 255, 128, 255]
+                        .map(|value| URational::new(value, 1))
+                        .to_vec(),
+                ),
+            ),
+        ];
+        ifd_entries.extend(image_data.ifd_entries());
+        ifd_entries.sort_by_key(ifd::Entry::tag);
+        let entry_count = encode_ifds(wrt, ifd_entries.into_iter());
+
+        IfdInfo {
+            inx: ifd_inx,
+            entry_count,
+        }
+    }
+}
+
+fn encode_ycbcr_img<C: Compression<colors::RGB>, E: EncodeEndianness>(
+    wrt: &mut TiffEncodeBuffer<E>,
+    image: &Image<colors::RGB>,
+    layout: Layout,
+    subsampling: ChromaSubsampling,
+    image_compressor: &C,
+) -> EncodeResult {
+    let (horizontal, vertical) = subsampling.factors();
+    let pixels = image.rows().collect::<Vec<_>>();
+
+    // Each data unit holds the luma of a block of pixels followed by the
+    // average chroma of the block
+    let unit_rows = (0..image.height().div_ceil(vertical))
+        .map(|unit_y| {
+            (0..image.width().div_ceil(horizontal))
+                .map(|unit_x| {
+                    let mut unit = Vec::with_capacity(horizontal * vertical + 2);
+                    let (mut cb_sum, mut cr_sum, mut count) = (0.0, 0.0, 0.0);
+                    for y in unit_y * vertical..(unit_y + 1) * vertical {
+                        for x in unit_x * horizontal..(unit_x + 1) * horizontal {
+                            // Blocks past the right and bottom edges repeat
+                            // the edge pixels
+                            let inside = x < image.width() && y < image.height();
+                            let pixel = pixels[y.min(image.height() - 1)][x.min(image.width() - 1)];
+                            let (luma, cb, cr) = rgb_to_ycbcr(pixel);
+                            unit.push(to_sample(luma));
+                            if inside {
+                                cb_sum += cb;
+                                cr_sum += cr;
+                                count += 1.0;
+                            }
+                        }
+                    }
+                    unit.push(to_sample(cb_sum / count));
+                    unit.push(to_sample(cr_sum / count));
+                    unit
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let rows = unit_rows.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    encode_subsampled_image_data(
+        wrt,
+        &rows,
+        (horizontal, vertical),
+        layout,
+        (horizontal * vertical + 2) * 8,
+        image_compressor,
+        |row| row.iter().flatten().copied(),
+    )
+}
+
+/// Converts to full range YCbCr with chroma centered on 128.
+fn rgb_to_ycbcr(colors::RGB { r, g, b }: colors::RGB) -> (f32, f32, f32) {
+    let [luma_red, luma_green, luma_blue] = LUMA_COEFFICIENTS;
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let luma = luma_red * r + luma_green * g + luma_blue * b;
+    let cb = (b - luma) / (2.0 - 2.0 * luma_blue) + 128.0;
+    let cr = (r - luma) / (2.0 - 2.0 * luma_red) + 128.0;
+    (luma, cb, cr)
+}
+
+fn to_sample(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::{rgb_to_ycbcr, ChromaSubsampling, YCbCrImageEncoder};
+    use crate::{
+        colors::RGB,
+        decode::{decode_images_with_options, DecodeOptions, DecodedImage},
+        encode::{
+            compression::{Lzw, NoCompression},
+            layout::TileSize,
+            ImageEncoder, Layout,
+        },
+        test_util::{decode_single, decoded_as},
+        Image,
+    };
+
+    const SUBSAMPLINGS: [ChromaSubsampling; 3] = [
+        ChromaSubsampling::Full,
+        ChromaSubsampling::Horizontal,
+        ChromaSubsampling::HorizontalAndVertical,
+    ];
+
+    /// A smooth gradient, so averaging the chroma of neighbouring pixels
+    /// changes them little.
+    fn gradient(width: usize, height: usize) -> Image<RGB> {
+        let pixels = (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| RGB::new(40 + 4 * x as u8, 60 + 3 * y as u8, 150))
+            })
+            .collect();
+        Image::new(pixels, width, height)
+    }
+
+    fn assert_close(decoded: &Image<RGB>, image: &Image<RGB>, tolerance: u8) {
+        assert_eq!(
+            (decoded.width(), decoded.height()),
+            (image.width(), image.height())
+        );
+        for (inx, (decoded, pixel)) in decoded
+            .iter_pixels()
+            .flatten()
+            .zip(image.iter_pixels().flatten())
+            .enumerate()
+        {
+            let differences = [
+                decoded.r.abs_diff(pixel.r),
+                decoded.g.abs_diff(pixel.g),
+                decoded.b.abs_diff(pixel.b),
+            ];
+            assert!(
+                differences
+                    .iter()
+                    .all(|&difference| difference <= tolerance),
+                "pixel {inx} differs by {differences:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rgb_to_ycbcr_formulas() {
+        let close = |(y, cb, cr): (f32, f32, f32), expected: (f32, f32, f32)| {
+            (y - expected.0).abs() < 0.01
+                && (cb - expected.1).abs() < 0.01
+                && (cr - expected.2).abs() < 0.01
+        };
+        assert!(close(rgb_to_ycbcr(RGB::new(0, 0, 0)), (0.0, 128.0, 128.0)));
+        assert!(close(
+            rgb_to_ycbcr(RGB::new(255, 255, 255)),
+            (255.0, 128.0, 128.0)
+        ));
+        assert!(close(
+            rgb_to_ycbcr(RGB::new(255, 0, 0)),
+            (76.245, 84.972, 255.5)
+        ));
+        assert!(close(
+            rgb_to_ycbcr(RGB::new(0, 0, 255)),
+            (29.07, 255.5, 107.265)
+        ));
+    }
+
+    #[test]
+    fn grays_round_trip_exactly() {
+        let pixels = (0..=255)
+            .map(|value| RGB::new(value, value, value))
+            .collect();
+        let image = Image::new(pixels, 16, 16);
+        for subsampling in SUBSAMPLINGS {
+            let encoded = YCbCrImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                .with_subsampling(subsampling)
+                .encode();
+            assert_close(&decoded_as(decode_single(&encoded)), &image, 0);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        // Odd widths and heights leave partial blocks at the right and
+        // bottom edges
+        for (width, height) in [(7, 5), (16, 16), (33, 17), (1, 1)] {
+            let image = gradient(width, height);
+            for subsampling in SUBSAMPLINGS {
+                // Subsampling is lossy, but the gradient keeps the error small
+                let tolerance = match subsampling {
+                    ChromaSubsampling::Full => 1,
+                    _ => 4,
+                };
+                for layout in [
+                    Layout::SingleStrip,
+                    Layout::RowsPerStrip(3),
+                    Layout::Tiles(TileSize::new(16, 16)),
+                ] {
+                    for encoded in [
+                        YCbCrImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                            .with_subsampling(subsampling)
+                            .with_layout(layout)
+                            .encode(),
+                        YCbCrImageEncoder::<BigEndian, _>::new(&image, Lzw)
+                            .with_subsampling(subsampling)
+                            .with_layout(layout)
+                            .encode(),
+                    ] {
+                        assert_close(&decoded_as(decode_single(&encoded)), &image, tolerance);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn planes_are_decoded_as_stored() {
+        let image = gradient(7, 5);
+        for (subsampling, (horizontal, vertical)) in [
+            (ChromaSubsampling::Full, (1, 1)),
+            (ChromaSubsampling::Horizontal, (2, 1)),
+            (ChromaSubsampling::HorizontalAndVertical, (2, 2)),
+        ] {
+            let encoded = YCbCrImageEncoder::<LittleEndian, _>::new(&image, NoCompression)
+                .with_subsampling(subsampling)
+                .with_layout(Layout::RowsPerStrip(2))
+                .encode();
+            let options = DecodeOptions::new().with_ycbcr_planes(true);
+            let mut decoded = decode_images_with_options(&encoded, options).unwrap();
+            assert!(decoded.errors.is_empty());
+            let Some(DecodedImage::YCbCrImage(planes)) = decoded.pages.pop().map(|page| page.image)
+            else {
+                panic!("not a YCbCr image");
+            };
+
+            assert_eq!(planes.subsampling(), (horizontal, vertical));
+            let luma = image
+                .iter_pixels()
+                .flatten()
+                .map(|pixel| rgb_to_ycbcr(pixel).0.round() as u8)
+                .collect::<Vec<_>>();
+            assert_eq!(planes.luma(), luma);
+            let blocks = 7usize.div_ceil(horizontal as usize) * 5usize.div_ceil(vertical as usize);
+            assert_eq!(planes.cb().len(), blocks);
+            assert_eq!(planes.cr().len(), blocks);
+            assert_close(&planes.to_rgb(), &image, 4);
+        }
+    }
+}
@@ -1,15 +1,47 @@
 pub(crate) mod tags;
 
-use crate::types::{Byte, Long, Short, URational};
+use byteorder::{ByteOrder, WriteBytesExt};
 
-/// Length of the entry count field in the IFD in bytes
-pub(crate) const ENTRY_COUNT_LEN: usize = 2;
-/// Length of the offset to the next IFD at the end of each IFD in bytes
-pub(crate) const NEXT_IFD_OFFSET_LEN: usize = 4;
+use crate::{
+    encode::EncodeEndianness,
+    types::{Byte, Long, SRational, Short, URational},
+};
 
-/// Returns the length of the IFD
-pub(crate) const fn get_len(fields: usize) -> usize {
-    ENTRY_COUNT_LEN + fields * Entry::LEN + NEXT_IFD_OFFSET_LEN
+/// Whether a file is a classic TIFF or a BigTIFF, which uses 64-bit offsets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Format {
+    Classic,
+    BigTiff,
+}
+
+impl Format {
+    /// Length of the entry count field in the IFD in bytes
+    pub(crate) const fn entry_count_len(self) -> usize {
+        match self {
+            Format::Classic => 2,
+            Format::BigTiff => 8,
+        }
+    }
+
+    /// Length of offsets in bytes, including the offset to the next IFD at
+    /// the end of each IFD and the value offset in each entry
+    pub(crate) const fn offset_len(self) -> usize {
+        match self {
+            Format::Classic => 4,
+            Format::BigTiff => 8,
+        }
+    }
+
+    /// Length of each entry in bytes
+    pub(crate) const fn entry_len(self) -> usize {
+        // Tag, field type, value count and value offset
+        2 + 2 + self.offset_len() + self.offset_len()
+    }
+
+    /// Returns the length of the IFD
+    pub(crate) const fn ifd_len(self, fields: usize) -> usize {
+        self.entry_count_len() + fields * self.entry_len() + self.offset_len()
+    }
 }
 
 pub(crate) struct Entry {
@@ -18,9 +50,6 @@ pub(crate) struct Entry {
 }
 
 impl Entry {
-    /// Length of each whole in bytes
-    pub(crate) const LEN: usize = 12;
-
     pub(crate) fn new(tag: Tag, values: Values) -> Self {
         Self { tag, values }
     }
@@ -40,9 +69,78 @@ impl Into<Values> for Entry {
     }
 }
 
+/// An entry with a tag this crate doesn't know, such as a private tag, kept
+/// as is so it can be written back when re-encoding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawEntry {
+    tag: u16,
+    field_type: Type,
+    /// ASCII values are kept as bytes so they are written back exactly
+    values: Values,
+}
+
+impl RawEntry {
+    pub(crate) fn new(tag: u16, field_type: Type, values: Values) -> Self {
+        Self {
+            tag,
+            field_type,
+            values,
+        }
+    }
+
+    pub fn tag(&self) -> u16 {
+        self.tag
+    }
+
+    pub fn field_type(&self) -> u16 {
+        self.field_type as u16
+    }
+
+    /// The number of values, not bytes.
+    pub fn count(&self) -> u64 {
+        self.values.num_values()
+    }
+
+    /// Returns the values as they would be stored in a file with the given
+    /// byte order.
+    pub fn bytes<E: EncodeEndianness>(&self) -> Vec<u8> {
+        self.values.to_bytes::<E>()
+    }
+
+    pub(crate) fn values(&self) -> &Values {
+        &self.values
+    }
+
+    /// Converts 64-bit values to longs so the entry can be written to a
+    /// classic TIFF, or returns `None` if they don't fit.
+    pub(crate) fn into_classic(self) -> Option<Self> {
+        match self.values {
+            Values::Long8s(long8s) | Values::Ifd8s(long8s) => {
+                let longs = long8s
+                    .into_iter()
+                    .map(Long::try_from)
+                    .collect::<Result<_, _>>()
+                    .ok()?;
+                Some(Self::new(self.tag, Type::Long, Values::Longs(longs)))
+            }
+            _ => Some(self),
+        }
+    }
+}
+
+impl From<Entry> for RawEntry {
+    fn from(entry: Entry) -> Self {
+        Self::new(
+            entry.tag as u16,
+            entry.values.field_type_tag(),
+            entry.values,
+        )
+    }
+}
+
 /// Represents the id representing each ifd field type.
 #[repr(u16)]
-#[derive(strum::FromRepr, Clone, Copy)]
+#[derive(strum::FromRepr, Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum Type {
     /// 8-bit unsigned integer.
     Byte = 1,
@@ -55,16 +153,41 @@ pub(crate) enum Type {
     /// Two LONGs: the first represents the numerator of a fraction; the second, the denominator.
     Rational = 5,
     // End of pre-TIFF 6 types
+    /// An 8-bit signed (twos-complement) integer.
+    SByte = 6,
+    /// An 8-bit byte that may contain anything, depending on the definition of the field.
+    Undefined = 7,
+    /// A 16-bit (2-byte) signed (twos-complement) integer.
+    SShort = 8,
+    /// A 32-bit (4-byte) signed (twos-complement) integer.
+    SLong = 9,
+    /// Two SLONG’s: the first represents the numerator of a fraction, the second the denominator.
+    SRational = 10,
+    /// Single precision (4-byte) IEEE format.
+    Float = 11,
+    /// Double precision (8-byte) IEEE format.
+    Double = 12,
 
-    // SByte,
-    // Undefined,
-    // SShort,
-    // SLong,
-    // SRational,
-    // Float,
-    // Double,
+    // BigTIFF types
+    /// 64-bit (8-byte) unsigned integer.
+    Long8 = 16,
+    /// 64-bit (8-byte) unsigned IFD offset.
+    Ifd8 = 18,
 }
 
+impl Type {
+    /// Length of each value in bytes
+    pub(crate) const fn size(self) -> usize {
+        match self {
+            Type::Byte | Type::ASCII | Type::SByte | Type::Undefined => 1,
+            Type::Short | Type::SShort => 2,
+            Type::Long | Type::SLong | Type::Float => 4,
+            Type::Rational | Type::SRational | Type::Double | Type::Long8 | Type::Ifd8 => 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Values {
     Bytes(Vec<Byte>),
     // According to the spec, only storing a single string is preferred when possible.
@@ -72,9 +195,31 @@ pub(crate) enum Values {
     Shorts(Vec<Short>),
     Longs(Vec<Long>),
     Rationals(Vec<URational>),
+    SBytes(Vec<i8>),
+    Undefined(Vec<Byte>),
+    SShorts(Vec<i16>),
+    SLongs(Vec<i32>),
+    SRationals(Vec<SRational>),
+    Floats(Vec<f32>),
+    Doubles(Vec<f64>),
+    Long8s(Vec<u64>),
+    Ifd8s(Vec<u64>),
 }
 
 impl Values {
+    /// Stores the values as shorts if they all fit, otherwise as longs if
+    /// they all fit, otherwise as long8s.
+    pub(crate) fn unsigned(values: Vec<u64>) -> Self {
+        let max = values.iter().copied().max().unwrap_or_default();
+        if max <= Short::MAX as u64 {
+            Values::Shorts(values.into_iter().map(|value| value as Short).collect())
+        } else if max <= Long::MAX as u64 {
+            Values::Longs(values.into_iter().map(|value| value as Long).collect())
+        } else {
+            Values::Long8s(values)
+        }
+    }
+
     pub(crate) const fn field_type_tag(&self) -> Type {
         match self {
             Values::Bytes(_) => Type::Byte,
@@ -82,16 +227,96 @@ impl Values {
             Values::Shorts(_) => Type::Short,
             Values::Longs(_) => Type::Long,
             Values::Rationals(_) => Type::Rational,
+            Values::SBytes(_) => Type::SByte,
+            Values::Undefined(_) => Type::Undefined,
+            Values::SShorts(_) => Type::SShort,
+            Values::SLongs(_) => Type::SLong,
+            Values::SRationals(_) => Type::SRational,
+            Values::Floats(_) => Type::Float,
+            Values::Doubles(_) => Type::Double,
+            Values::Long8s(_) => Type::Long8,
+            Values::Ifd8s(_) => Type::Ifd8,
+        }
+    }
+
+    /// Returns the values in the given byte order.
+    pub(crate) fn to_bytes<E: ByteOrder>(&self) -> Vec<u8> {
+        let mut value_bytes = Vec::new();
+        match self {
+            Values::Bytes(bytes) | Values::Undefined(bytes) => value_bytes.extend(bytes),
+            Values::ASCII(string) => {
+                value_bytes.extend(string.as_bytes());
+                // Termainting NUL char
+                value_bytes.push(0);
+            }
+            Values::Shorts(shorts) => {
+                for short in shorts.iter() {
+                    value_bytes.write_u16::<E>(*short).unwrap();
+                }
+            }
+            Values::Longs(longs) => {
+                for long in longs.iter() {
+                    value_bytes.write_u32::<E>(*long).unwrap();
+                }
+            }
+            Values::Rationals(rationals) => {
+                for rat in rationals.iter() {
+                    value_bytes.write_u32::<E>(rat.numerator).unwrap();
+                    value_bytes.write_u32::<E>(rat.denominator).unwrap();
+                }
+            }
+            Values::SBytes(sbytes) => value_bytes.extend(sbytes.iter().map(|&sbyte| sbyte as u8)),
+            Values::SShorts(sshorts) => {
+                for sshort in sshorts.iter() {
+                    value_bytes.write_i16::<E>(*sshort).unwrap();
+                }
+            }
+            Values::SLongs(slongs) => {
+                for slong in slongs.iter() {
+                    value_bytes.write_i32::<E>(*slong).unwrap();
+                }
+            }
+            Values::SRationals(srationals) => {
+                for rat in srationals.iter() {
+                    value_bytes.write_i32::<E>(rat.numerator).unwrap();
+                    value_bytes.write_i32::<E>(rat.denominator).unwrap();
+                }
+            }
+            Values::Floats(floats) => {
+                for float in floats.iter() {
+                    value_bytes.write_f32::<E>(*float).unwrap();
+                }
+            }
+            Values::Doubles(doubles) => {
+                for double in doubles.iter() {
+                    value_bytes.write_f64::<E>(*double).unwrap();
+                }
+            }
+            Values::Long8s(long8s) | Values::Ifd8s(long8s) => {
+                for long8 in long8s.iter() {
+                    value_bytes.write_u64::<E>(*long8).unwrap();
+                }
+            }
         }
+        value_bytes
     }
 
-    pub(crate) fn num_values(&self) -> Long {
+    pub(crate) fn num_values(&self) -> u64 {
         match self {
-            Values::Bytes(bytes) => bytes.len().try_into().unwrap(),
-            Values::ASCII(_) => 1,
-            Values::Shorts(short) => short.len().try_into().unwrap(),
-            Values::Longs(long) => long.len().try_into().unwrap(),
-            Values::Rationals(rational) => rational.len().try_into().unwrap(),
+            Values::Bytes(bytes) => bytes.len() as u64,
+            Values::ASCII(string) => string.len() as u64 + 1,
+            Values::Shorts(short) => short.len() as u64,
+            Values::Longs(long) => long.len() as u64,
+            Values::Rationals(rational) => rational.len() as u64,
+            Values::SBytes(sbyte) => sbyte.len() as u64,
+            Values::Undefined(undefined) => undefined.len() as u64,
+            Values::SShorts(sshort) => sshort.len() as u64,
+            Values::SLongs(slong) => slong.len() as u64,
+            Values::SRationals(srational) => srational.len() as u64,
+            Values::Floats(float) => float.len() as u64,
+            Values::Doubles(double) => double.len() as u64,
+            Values::Long8s(long8) => long8.len() as u64,
+            Values::Ifd8s(ifd8) => ifd8.len() as u64,
         }
     }
 }
@@ -105,6 +330,14 @@ pub enum Tag {
     BitsPerSample = 258,
     Compression = 259,
     PhotometricInterpretation = 262,
+    /// The name of the document from which this image was scanned.
+    DocumentName = 269,
+    /// A string that describes the subject of the image.
+    ImageDescription = 270,
+    /// The scanner manufacturer.
+    Make = 271,
+    /// The scanner model name or number.
+    Model = 272,
     /// For each strip, the byte offset of that strip.
     StripOffsets = 273,
     SamplesPerPixel = 277,
@@ -117,14 +350,48 @@ pub enum Tag {
     /// The number of pixels per ResolutionUnit in the ImageLength (typically, vertical) direction.
     YResolution = 283,
     PlanarConfiguration = 284,
+    /// The name of the page from which this image was scanned.
+    PageName = 285,
     ResolutionUnit = 296,
+    /// Name and version number of the software package(s) used to create the image.
+    Software = 305,
+    /// Date and time of image creation.
+    DateTime = 306,
+    /// Person who created the image.
+    Artist = 315,
+    /// The computer and/or operating system in use at the time of image creation.
+    HostComputer = 316,
     ColorMap = 320,
     TileWidth = 322,
     TileLength = 323,
     TileOffsets = 324,
     TileByteCounts = 325,
+    /// The set of inks used in a separated image.
+    InkSet = 332,
+    /// The name of each ink used in a separated image.
+    InkNames = 333,
+    /// The number of inks in a separated image.
+    NumberOfInks = 334,
+    /// Description of extra components, such as alpha.
+    ExtraSamples = 338,
+    /// How to interpret each data sample in a pixel.
+    SampleFormat = 339,
+    /// The minimum sample value, in the format of the samples.
+    SMinSampleValue = 340,
+    /// The maximum sample value, in the format of the samples.
+    SMaxSampleValue = 341,
     JPEGProc = 512,
     JPEGQTables = 519,
     JPEGDCTables = 520,
     JPEGACTables = 521,
+    /// The coefficients used to convert from RGB to YCbCr.
+    YCbCrCoefficients = 529,
+    /// The horizontal and vertical subsampling of the chrominance samples.
+    YCbCrSubSampling = 530,
+    /// Where the chrominance samples are relative to the luminance samples.
+    YCbCrPositioning = 531,
+    /// The reference black and white point of each sample.
+    ReferenceBlackWhite = 532,
+    /// Copyright notice.
+    Copyright = 33432,
 }
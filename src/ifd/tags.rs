@@ -6,6 +6,17 @@ pub enum PhotometricInterpretation {
     BlackIsZero = 1,
     RGB = 2,
     PaletteColor = 3,
+    /// Separated into inks, usually CMYK.
+    Separated = 5,
+    /// Luminance and chrominance, with the chrominance possibly subsampled.
+    YCbCr = 6,
+    /// CIE L*a*b* with signed a* and b* samples
+    CIELab = 8,
+    /// CIE L*a*b* with a* and b* samples offset by 128, as used by ICC profiles
+    ICCLab = 9,
+    /// CIE L*a*b* as used by fax (TIFF-FX), with samples scaled to the
+    /// default ITU ranges
+    ITULab = 10,
 }
 
 /// [`super::ifd::IfdFieldTag::Compression`]
@@ -26,16 +37,82 @@ impl Default for Compression {
 }
 
 /// [`super::ifd::IfdFieldTag::ResolutionUnit`]
-#[derive(strum::FromRepr, Clone, Copy)]
+#[derive(strum::FromRepr, Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[repr(u16)]
 pub enum ResolutionUnit {
     /// No absolute unit of measurement
     NoUnit = 1,
+    #[default]
     Inch = 2,
     Centimeter = 3,
 }
-impl Default for ResolutionUnit {
-    fn default() -> Self {
-        Self::Inch
+
+/// [`super::ifd::IfdFieldTag::PlanarConfiguration`]
+#[derive(strum::FromRepr, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u16)]
+pub enum PlanarConfiguration {
+    /// The samples of each pixel are stored together.
+    #[default]
+    Chunky = 1,
+    /// Each sample is stored in a separate plane, with the strips or tiles
+    /// of each plane stored one plane after another.
+    Planar = 2,
+}
+
+/// [`super::ifd::IfdFieldTag::ExtraSamples`]
+#[derive(strum::FromRepr, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u16)]
+pub enum ExtraSamples {
+    Unspecified = 0,
+    /// Alpha data with the color samples premultiplied by it
+    AssociatedAlpha = 1,
+    UnassociatedAlpha = 2,
+}
+
+impl From<crate::colors::Alpha> for ExtraSamples {
+    fn from(alpha: crate::colors::Alpha) -> Self {
+        match alpha {
+            crate::colors::Alpha::Associated => Self::AssociatedAlpha,
+            crate::colors::Alpha::Unassociated => Self::UnassociatedAlpha,
+        }
     }
 }
+
+/// [`super::ifd::IfdFieldTag::InkSet`]
+#[derive(strum::FromRepr, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u16)]
+pub enum InkSet {
+    /// Cyan, magenta, yellow and black inks, in that order.
+    #[default]
+    Cmyk = 1,
+    /// Inks described by the InkNames field.
+    NotCmyk = 2,
+}
+
+/// [`super::ifd::IfdFieldTag::YCbCrPositioning`]
+#[derive(strum::FromRepr, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u16)]
+pub enum YCbCrPositioning {
+    /// Each chrominance sample is at the center of the luminance samples it
+    /// covers.
+    #[default]
+    Centered = 1,
+    /// Each chrominance sample is at the same position as the first
+    /// luminance sample it covers.
+    Cosited = 2,
+}
+
+/// [`super::ifd::IfdFieldTag::SampleFormat`]
+#[derive(strum::FromRepr, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u16)]
+pub enum SampleFormat {
+    /// Unsigned integer data
+    #[default]
+    Unsigned = 1,
+    /// Two's complement signed integer data
+    Signed = 2,
+    /// IEEE floating point data
+    Float = 3,
+    /// Undefined data format
+    Undefined = 4,
+}
@@ -2,11 +2,16 @@ use std::{error::Error, fmt::Display, slice::ChunksExact};
 
 use colors::Color;
 
+mod bits;
 pub mod colors;
 mod compression;
 pub mod decode;
 pub mod encode;
 pub mod ifd;
+pub mod metadata;
+pub mod resolution;
+#[cfg(test)]
+mod test_util;
 mod types;
 
 pub struct Image<C: Color> {
@@ -0,0 +1,190 @@
+use crate::ifd;
+
+/// Descriptive information about an image, stored as ASCII fields.
+///
+/// Fields that are `None` are not written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// A description of the subject of the image.
+    pub image_description: Option<String>,
+    /// The manufacturer of the scanner, camera or other device that created
+    /// the image.
+    pub make: Option<String>,
+    /// The model name or number of the device that created the image.
+    pub model: Option<String>,
+    /// The name and version of the software that created the image.
+    pub software: Option<String>,
+    /// The date and time the image was created, formatted as
+    /// `YYYY:MM:DD HH:MM:SS` with a 24-hour clock.
+    pub date_time: Option<String>,
+    /// The person who created the image.
+    pub artist: Option<String>,
+    /// The computer and/or operating system in use when the image was
+    /// created.
+    pub host_computer: Option<String>,
+    /// The copyright notice of the image.
+    pub copyright: Option<String>,
+    /// The name of the document the image was scanned from.
+    pub document_name: Option<String>,
+    /// The name of the page the image was scanned from.
+    pub page_name: Option<String>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tags of the metadata fields, in ascending order.
+    pub(crate) const TAGS: [ifd::Tag; 10] = [
+        ifd::Tag::DocumentName,
+        ifd::Tag::ImageDescription,
+        ifd::Tag::Make,
+        ifd::Tag::Model,
+        ifd::Tag::PageName,
+        ifd::Tag::Software,
+        ifd::Tag::DateTime,
+        ifd::Tag::Artist,
+        ifd::Tag::HostComputer,
+        ifd::Tag::Copyright,
+    ];
+
+    /// Returns the entries of the fields that are set.
+    pub(crate) fn ifd_entries(&self) -> Vec<ifd::Entry> {
+        Self::TAGS
+            .into_iter()
+            .filter_map(|tag| {
+                self.field(tag)
+                    .as_ref()
+                    .map(|value| ifd::Entry::new(tag, ifd::Values::ASCII(value.clone())))
+            })
+            .collect()
+    }
+
+    fn field(&self, tag: ifd::Tag) -> &Option<String> {
+        match tag {
+            ifd::Tag::DocumentName => &self.document_name,
+            ifd::Tag::ImageDescription => &self.image_description,
+            ifd::Tag::Make => &self.make,
+            ifd::Tag::Model => &self.model,
+            ifd::Tag::PageName => &self.page_name,
+            ifd::Tag::Software => &self.software,
+            ifd::Tag::DateTime => &self.date_time,
+            ifd::Tag::Artist => &self.artist,
+            ifd::Tag::HostComputer => &self.host_computer,
+            ifd::Tag::Copyright => &self.copyright,
+            _ => unreachable!("not a metadata tag"),
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the tag is not one of [`Self::TAGS`]
+    pub(crate) fn field_mut(&mut self, tag: ifd::Tag) -> &mut Option<String> {
+        match tag {
+            ifd::Tag::DocumentName => &mut self.document_name,
+            ifd::Tag::ImageDescription => &mut self.image_description,
+            ifd::Tag::Make => &mut self.make,
+            ifd::Tag::Model => &mut self.model,
+            ifd::Tag::PageName => &mut self.page_name,
+            ifd::Tag::Software => &mut self.software,
+            ifd::Tag::DateTime => &mut self.date_time,
+            ifd::Tag::Artist => &mut self.artist,
+            ifd::Tag::HostComputer => &mut self.host_computer,
+            ifd::Tag::Copyright => &mut self.copyright,
+            _ => unreachable!("not a metadata tag"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::Metadata;
+    use crate::{
+        colors::Grayscale8Bit,
+        decode::{decode_images, DecodedPage},
+        encode::{compression::NoCompression, grayscale_8bit::*, ImageEncoder},
+        Image,
+    };
+
+    fn decode_page(encoded: &[u8]) -> DecodedPage {
+        let mut decoded = decode_images(encoded).unwrap();
+        assert!(decoded.errors.is_empty(), "{:?}", decoded.errors);
+        decoded.pages.remove(0)
+    }
+
+    fn full_metadata() -> Metadata {
+        Metadata {
+            image_description: Some("A gray square".to_string()),
+            make: Some("Make".to_string()),
+            model: Some("Model 2".to_string()),
+            software: Some("tiff".to_string()),
+            date_time: Some("2024:01:02 13:04:05".to_string()),
+            artist: Some("Someone".to_string()),
+            host_computer: Some("Host".to_string()),
+            copyright: Some("Copyright notice".to_string()),
+            document_name: Some("Document".to_string()),
+            page_name: Some("Page 1".to_string()),
+        }
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        let image = Image::new(vec![Grayscale8Bit(1); 4], 2, 2);
+        let partial = Metadata {
+            make: Some("Make".to_string()),
+            // Short enough to fit in the entry
+            model: Some("M2".to_string()),
+            ..Metadata::new()
+        };
+        for metadata in [full_metadata(), partial, Metadata::new()] {
+            for encoded in [
+                Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+                    &image,
+                    NoCompression,
+                    BlackIsZero,
+                )
+                .with_metadata(&metadata)
+                .encode(),
+                Grayscale8BitImageEncoder::<BigEndian, _, _>::new(
+                    &image,
+                    NoCompression,
+                    BlackIsZero,
+                )
+                .with_metadata(&metadata)
+                .encode_bigtiff(),
+            ] {
+                let page = decode_page(&encoded);
+                assert_eq!(page.metadata, metadata);
+                assert!(page.raw_entries.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn later_metadata_replaces_earlier_fields() {
+        let image = Image::new(vec![Grayscale8Bit(1); 4], 2, 2);
+        let first = full_metadata();
+        let second = Metadata {
+            artist: Some("Someone else".to_string()),
+            ..Metadata::new()
+        };
+        let encoded = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+            &image,
+            NoCompression,
+            BlackIsZero,
+        )
+        .with_metadata(&first)
+        .with_metadata(&second)
+        .encode();
+        assert_eq!(
+            decode_page(&encoded).metadata,
+            Metadata {
+                artist: Some("Someone else".to_string()),
+                ..full_metadata()
+            }
+        );
+    }
+}
@@ -0,0 +1,159 @@
+pub use crate::{ifd::tags::ResolutionUnit, types::URational};
+
+use crate::ifd;
+
+/// The number of pixels per [`ResolutionUnit`] across and down an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    /// Pixels per unit in the direction of the image width
+    pub x: URational,
+    /// Pixels per unit in the direction of the image length
+    pub y: URational,
+    pub unit: ResolutionUnit,
+}
+
+impl Resolution {
+    pub fn new(x: URational, y: URational, unit: ResolutionUnit) -> Self {
+        Self { x, y, unit }
+    }
+
+    /// The same number of dots per inch in both directions.
+    pub fn dpi(dpi: u32) -> Self {
+        let dpi = URational::new(dpi, 1);
+        Self::new(dpi, dpi, ResolutionUnit::Inch)
+    }
+
+    pub(crate) fn ifd_entries(&self) -> Vec<ifd::Entry> {
+        vec![
+            ifd::Entry::new(ifd::Tag::XResolution, ifd::Values::Rationals(vec![self.x])),
+            ifd::Entry::new(ifd::Tag::YResolution, ifd::Values::Rationals(vec![self.y])),
+            ifd::Entry::new(
+                ifd::Tag::ResolutionUnit,
+                ifd::Values::Shorts(vec![self.unit as u16]),
+            ),
+        ]
+    }
+}
+
+/// One pixel per inch, which is what images were written with before the
+/// resolution could be set.
+impl Default for Resolution {
+    fn default() -> Self {
+        Self::dpi(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::{Resolution, ResolutionUnit, URational};
+    use crate::{
+        colors::Grayscale8Bit,
+        decode::{decode_images, DecodedPage},
+        encode::{compression::NoCompression, grayscale_8bit::*, ImageEncoder},
+        metadata::Metadata,
+        Image,
+    };
+
+    fn decode_page(encoded: &[u8]) -> DecodedPage {
+        let mut decoded = decode_images(encoded).unwrap();
+        assert!(decoded.errors.is_empty(), "{:?}", decoded.errors);
+        decoded.pages.remove(0)
+    }
+
+    fn image() -> Image<Grayscale8Bit> {
+        Image::new(vec![Grayscale8Bit(1); 4], 2, 2)
+    }
+
+    #[test]
+    fn default_is_one_dpi() {
+        assert_eq!(Resolution::default(), Resolution::dpi(1));
+        let encoded = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+            &image(),
+            NoCompression,
+            BlackIsZero,
+        )
+        .encode();
+        let page = decode_page(&encoded);
+        assert_eq!(
+            page.resolution,
+            Resolution::new(
+                URational::new(1, 1),
+                URational::new(1, 1),
+                ResolutionUnit::Inch
+            )
+        );
+        assert!(page.raw_entries.is_empty());
+    }
+
+    #[test]
+    fn resolution_round_trip() {
+        let resolutions = [
+            Resolution::dpi(300),
+            Resolution::new(
+                URational::new(1181, 10),
+                URational::new(59, 1),
+                ResolutionUnit::Centimeter,
+            ),
+            Resolution::new(
+                URational::new(3, 2),
+                URational::new(1, 1),
+                ResolutionUnit::NoUnit,
+            ),
+        ];
+        for resolution in resolutions {
+            for encoded in [
+                Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+                    &image(),
+                    NoCompression,
+                    BlackIsZero,
+                )
+                .with_resolution(resolution)
+                .encode(),
+                Grayscale8BitImageEncoder::<BigEndian, _, _>::new(
+                    &image(),
+                    NoCompression,
+                    BlackIsZero,
+                )
+                .with_resolution(resolution)
+                .encode_bigtiff(),
+            ] {
+                let page = decode_page(&encoded);
+                assert_eq!(page.resolution, resolution);
+                assert!(page.raw_entries.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn chained_with_metadata_and_later_resolution_wins() {
+        let metadata = Metadata {
+            software: Some("tiff".to_string()),
+            ..Metadata::new()
+        };
+        let encoded = Grayscale8BitImageEncoder::<LittleEndian, _, _>::new(
+            &image(),
+            NoCompression,
+            BlackIsZero,
+        )
+        .with_resolution(Resolution::dpi(72))
+        .with_metadata(&metadata)
+        .with_resolution(Resolution::new(
+            URational::new(40, 1),
+            URational::new(20, 1),
+            ResolutionUnit::Centimeter,
+        ))
+        .encode();
+        let page = decode_page(&encoded);
+        assert_eq!(page.metadata, metadata);
+        assert_eq!(
+            page.resolution,
+            Resolution::new(
+                URational::new(40, 1),
+                URational::new(20, 1),
+                ResolutionUnit::Centimeter
+            )
+        );
+    }
+}
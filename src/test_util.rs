@@ -0,0 +1,120 @@
+//! Helpers shared by the unit tests.
+
+use crate::{
+    colors::{self, AlphaColor, Color},
+    decode::{decode_images, AlphaImage, CMYKImage, DecodedImage, FloatImage},
+    Image,
+};
+
+/// Decodes a file holding a single image, failing on any decode error.
+pub(crate) fn decode_single(encoded: &[u8]) -> DecodedImage {
+    let decoded = decode_images(encoded).unwrap();
+    assert!(decoded.errors.is_empty(), "{:?}", decoded.errors);
+    assert_eq!(decoded.pages.len(), 1);
+    decoded.pages.into_iter().next().unwrap().image
+}
+
+/// A color that an image of it is decoded back to.
+pub(crate) trait DecodesTo: Color + Sized {
+    fn from_decoded(image: DecodedImage) -> Option<Image<Self>>;
+}
+
+/// Implements [`DecodesTo`] for colors decoded as the given variants,
+/// optionally taking the image out of the variant's value with a function.
+macro_rules! decodes_to {
+    ($($color:ty => $variant:ident $(via $into_image:path)?),* $(,)?) => {$(
+        impl DecodesTo for $color {
+            fn from_decoded(image: DecodedImage) -> Option<Image<Self>> {
+                match image {
+                    DecodedImage::$variant(image) => Some($($into_image)?(image)),
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+decodes_to!(
+    colors::Bilevel => BilevelImage,
+    colors::Grayscale8Bit => Grayscale8BitImage,
+    colors::Grayscale4Bit => Grayscale4BitImage,
+    colors::Grayscale16Bit => Grayscale16BitImage,
+    colors::RGB => RGBImage,
+    colors::RGB16 => RGB16Image,
+    colors::RGBA => RGBAImage via stored_image,
+    colors::GrayscaleAlpha => GrayscaleAlphaImage via stored_image,
+    colors::CMYK => CMYKImage via CMYKImage::into_image,
+    colors::Lab => LabImage,
+    colors::GrayscaleF16 => GrayscaleF16Image via FloatImage::into_image,
+    colors::GrayscaleF32 => GrayscaleF32Image via FloatImage::into_image,
+    colors::GrayscaleF64 => GrayscaleF64Image via FloatImage::into_image,
+    colors::RGBF16 => RGBF16Image via FloatImage::into_image,
+    colors::RGBF32 => RGBF32Image via FloatImage::into_image,
+    colors::RGBF64 => RGBF64Image via FloatImage::into_image,
+    colors::GrayscaleI8 => GrayscaleI8Image,
+    colors::GrayscaleI16 => GrayscaleI16Image,
+    colors::GrayscaleI32 => GrayscaleI32Image,
+    colors::RGBI8 => RGBI8Image,
+    colors::RGBI16 => RGBI16Image,
+    colors::RGBI32 => RGBI32Image,
+);
+
+/// Returns the image with its colors as stored in the file.
+fn stored_image<C: AlphaColor<ViewAs = C>>(image: AlphaImage<C>) -> Image<C> {
+    let alpha = image.alpha();
+    image.into_image(alpha)
+}
+
+/// Returns the decoded image as an image of `C`, failing if it was decoded
+/// as another kind of image.
+pub(crate) fn decoded_as<C: DecodesTo>(image: DecodedImage) -> Image<C> {
+    C::from_decoded(image)
+        .unwrap_or_else(|| panic!("not an image of {}", std::any::type_name::<C>()))
+}
+
+/// Asserts that both images have the same size and pixels.
+pub(crate) fn assert_same_pixels<C>(decoded: &Image<C>, image: &Image<C>)
+where
+    C: Color,
+    C::ViewAs: PartialEq,
+{
+    assert_eq!(
+        (decoded.width(), decoded.height()),
+        (image.width(), image.height())
+    );
+    let first_difference = decoded
+        .iter_pixels()
+        .flatten()
+        .zip(image.iter_pixels().flatten())
+        .position(|(decoded, pixel)| decoded != pixel);
+    assert_eq!(first_difference, None, "pixels differ");
+}
+
+/// Asserts that `encoded` holds a single image with the pixels of `image`.
+pub(crate) fn assert_decodes_to<C>(encoded: &[u8], image: &Image<C>)
+where
+    C: DecodesTo,
+    C::ViewAs: PartialEq,
+{
+    assert_same_pixels(&decoded_as(decode_single(encoded)), image);
+}
+
+/// Builds a little endian classic TIFF with `data` right after the header
+/// and an IFD with the given tag, type, count and value offset entries after
+/// that.
+pub(crate) fn handmade_tiff(data: &[u8], entries: &[(u16, u16, u32, u32)]) -> Vec<u8> {
+    let ifd_offset = 8 + data.len().next_multiple_of(2) as u32;
+    let mut bytes = vec![b'I', b'I', 42, 0];
+    bytes.extend(ifd_offset.to_le_bytes());
+    bytes.extend(data);
+    bytes.resize(ifd_offset as usize, 0);
+    bytes.extend((entries.len() as u16).to_le_bytes());
+    for &(tag, field_type, count, value_offset) in entries {
+        bytes.extend(tag.to_le_bytes());
+        bytes.extend(field_type.to_le_bytes());
+        bytes.extend(count.to_le_bytes());
+        bytes.extend(value_offset.to_le_bytes());
+    }
+    bytes.extend(0u32.to_le_bytes());
+    bytes
+}
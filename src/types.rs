@@ -2,17 +2,28 @@ pub(crate) type Byte = u8;
 pub(crate) type Short = u16;
 pub(crate) type Long = u32;
 
-#[derive(Clone, Copy)]
-pub(crate) struct URational {
+/// An unsigned fraction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct URational {
     pub numerator: u32,
     pub denominator: u32,
 }
 
 impl URational {
-    pub(crate) fn new(numerator: u32, denominator: u32) -> Self {
+    pub fn new(numerator: u32, denominator: u32) -> Self {
         Self {
             numerator,
             denominator,
         }
     }
+
+    pub fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SRational {
+    pub numerator: i32,
+    pub denominator: i32,
 }